@@ -1,22 +1,261 @@
 use super::tree_rewriter::Rewriter;
-use crate::parsing::ast::{Program, Stmt};
+use super::Optimization;
+use crate::parsing::ast::{EnumVariant, Pattern, Program, Stmt};
 use crate::parsing::lexer::{Token, TokenKind};
 use crate::Expr;
+use ordered_float::NotNan;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
-pub(super) struct Folder {}
+pub(super) struct Folder {
+    log: Vec<Optimization>,
+    /// folded values of top-level constants, substituted at every use
+    consts: HashMap<String, Expr>,
+    /// names declared inside functions and blocks being visited, innermost last.
+    /// Top-level names never clash with constants, so top level is not tracked
+    locals: Vec<HashSet<String>>,
+}
 
 impl Folder {
-    pub fn fold_constants(program: Program) -> Result<Program, String> {
-        let mut f = Folder {};
+    pub fn fold_constants(program: Program) -> Result<(Program, Vec<Optimization>), String> {
+        let mut f = Folder {
+            log: vec![],
+            consts: HashMap::new(),
+            locals: vec![],
+        };
+
+        //constants are visible in functions declared before them, so all of them
+        //are folded first. Invalid initializers are reported when visiting declaration
+        for stmt in &program {
+            if let Stmt::ConstDeclaration(name, value) = stmt {
+                let value = f.visit_expr(value.clone())?;
+                if Folder::is_literal(&value) {
+                    f.consts
+                        .insert(name.get_string().unwrap().to_string(), value);
+                }
+            }
+        }
+
+        let program = program
+            .into_iter()
+            .map(|s| f.visit_stmt(s))
+            .collect::<Result<Program, String>>()?;
+
+        Ok((program, f.log))
+    }
+
+    fn is_literal(expr: &Expr) -> bool {
+        matches!(
+            expr,
+            Expr::Number(..) | Expr::FloatNumber(..) | Expr::Bool(..) | Expr::ConstString(..)
+        )
+    }
+
+    /// whether literal counts as true in conditions, only `false` and `Nothing` do not
+    fn truthiness(expr: &Expr) -> Option<bool> {
+        match expr {
+            Expr::Bool(t) => Some(t.kind == TokenKind::True),
+            other => Folder::is_literal(other).then_some(true),
+        }
+    }
 
-        program.into_iter().map(|s| f.visit_stmt(s)).collect()
+    fn literal_string(expr: &Expr) -> String {
+        match expr {
+            Expr::Number(t) => t.get_number().unwrap().to_string(),
+            Expr::FloatNumber(t) => t.get_float().unwrap().to_string(),
+            Expr::Bool(t) => (t.kind == TokenKind::True).to_string(),
+            Expr::ConstString(t) => format!("{:?}", t.get_string().unwrap()),
+            _ => unreachable!("only literals are folded"),
+        }
+    }
+
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.locals.last_mut() {
+            scope.insert(name.get_string().unwrap().to_string());
+        }
+    }
+
+    fn is_shadowed(&self, name: &str) -> bool {
+        self.locals.iter().any(|scope| scope.contains(name))
+    }
+
+    fn visit_function(
+        &mut self,
+        args: &[Token],
+        vararg: Option<&Token>,
+        body: Expr,
+    ) -> Result<Expr, String> {
+        self.locals.push(HashSet::new());
+        for arg in args.iter().chain(vararg) {
+            self.declare(arg);
+        }
+        let body = self.visit_expr(body);
+        self.locals.pop();
+        body
+    }
+
+    /// literals are never callable, so calling one is reported before running program
+    fn check_callable(target: &Expr) -> Result<(), String> {
+        let (literal, token) = match target {
+            Expr::Number(t) => ("Int", t),
+            Expr::FloatNumber(t) => ("Float", t),
+            Expr::Bool(t) => ("Bool", t),
+            Expr::ConstString(t) => ("String", t),
+            _ => return Ok(()),
+        };
+        Err(format!(
+            "cannot call {} literal [{}]",
+            literal, token.position
+        ))
     }
 }
 
 impl Rewriter<String> for Folder {
-    fn visit_assert_statement(&mut self, keyword: Token, expr: Expr) -> Result<Stmt, String> {
-        //do not touch asserts
-        Ok(Stmt::Assert(keyword, expr))
+    fn visit_assert_statement(
+        &mut self,
+        keyword: Token,
+        expr: Expr,
+        span: Range<usize>,
+        message: Option<Expr>,
+    ) -> Result<Stmt, String> {
+        //do not touch asserts, only reject messages that can never be strings
+        if let Some(Expr::Number(t) | Expr::FloatNumber(t) | Expr::Bool(t)) = &message {
+            return Err(format!(
+                "assertion message must be a string [{}]",
+                t.position
+            ));
+        }
+        Ok(Stmt::Assert(keyword, expr, span, message))
+    }
+
+    fn visit_const_stmt(&mut self, name: Token, value: Expr) -> Result<Stmt, String> {
+        if let Some(value) = self.consts.get(name.get_string().unwrap()) {
+            return Ok(Stmt::ConstDeclaration(name, value.clone()));
+        }
+        match self.visit_expr(value)? {
+            value if Folder::is_literal(&value) => Ok(Stmt::ConstDeclaration(name, value)),
+            _ => Err(format!(
+                "initializer of const {} [{}] is not a constant expression",
+                name.get_string().unwrap(),
+                name.position
+            )),
+        }
+    }
+
+    fn visit_var_stmt(&mut self, name: Token, rhs: Option<Expr>) -> Result<Stmt, String> {
+        let rhs = rhs.map(|rhs| self.visit_expr(rhs)).transpose()?;
+        self.declare(&name);
+        Ok(Stmt::VarDeclaration(name, rhs))
+    }
+
+    fn visit_destructuring_stmt(&mut self, pattern: Pattern, value: Expr) -> Result<Stmt, String> {
+        let value = self.visit_expr(value)?;
+        for name in pattern.names() {
+            self.declare(name);
+        }
+        Ok(Stmt::DestructuringDeclaration(pattern, value))
+    }
+
+    fn visit_function_declaration_statement(
+        &mut self,
+        name: Token,
+        args: Vec<Token>,
+        vararg: Option<Token>,
+        body: Expr,
+    ) -> Result<Stmt, String> {
+        self.declare(&name);
+        let body = self.visit_function(&args, vararg.as_ref(), body)?;
+        Ok(Stmt::FunctionDeclaration {
+            name,
+            args,
+            vararg,
+            body,
+        })
+    }
+
+    fn visit_struct_declaration_statement(
+        &mut self,
+        name: Token,
+        fields: Vec<Token>,
+    ) -> Result<Stmt, String> {
+        self.declare(&name);
+        Ok(Stmt::StructDeclaration { name, fields })
+    }
+
+    fn visit_enum_declaration(
+        &mut self,
+        name: Token,
+        variants: Vec<EnumVariant>,
+    ) -> Result<Stmt, String> {
+        self.declare(&name);
+        Ok(Stmt::EnumDeclaration { name, variants })
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        module: Vec<Token>,
+        name: Token,
+        rename: Option<Token>,
+    ) -> Result<Stmt, String> {
+        self.declare(rename.as_ref().unwrap_or(&name));
+        Ok(Stmt::Import {
+            module,
+            name,
+            rename,
+        })
+    }
+
+    fn visit_variable_expr(&mut self, variable_name: Token) -> Result<Expr, String> {
+        let name = variable_name.get_string().unwrap();
+        let value = match self.consts.get(name) {
+            Some(value) if !self.is_shadowed(name) => value,
+            _ => return Ok(Expr::Name(variable_name)),
+        };
+
+        //literal keeps position of use, so later errors point at it
+        let at_use = |t: &Token| Token {
+            position: variable_name.position,
+            kind: t.kind.clone(),
+        };
+        let literal = match value {
+            Expr::Number(t) => Expr::Number(at_use(t)),
+            Expr::FloatNumber(t) => Expr::FloatNumber(at_use(t)),
+            Expr::Bool(t) => Expr::Bool(at_use(t)),
+            Expr::ConstString(t) => Expr::ConstString(at_use(t)),
+            _ => unreachable!("only literals are stored as constants"),
+        };
+
+        self.log.push(Optimization::ConstantSubstituted {
+            position: variable_name.position,
+            name: name.to_string(),
+        });
+        Ok(literal)
+    }
+
+    fn visit_block(
+        &mut self,
+        start_token: Token,
+        end_token: Token,
+        containing_statements: Vec<Stmt>,
+    ) -> Result<Expr, String> {
+        self.locals.push(HashSet::new());
+        let statements = containing_statements
+            .into_iter()
+            .map(|stmt| self.visit_stmt(stmt))
+            .collect::<Result<Vec<Stmt>, String>>();
+        self.locals.pop();
+        Ok(Expr::Block(start_token, end_token, statements?))
+    }
+
+    fn visit_anon_function_expr(
+        &mut self,
+        args: Vec<Token>,
+        vararg: Option<Token>,
+        arrow: Token,
+        body: Box<Expr>,
+    ) -> Result<Expr, String> {
+        let body = self.visit_function(&args, vararg.as_ref(), *body)?;
+        Ok(Expr::AnonFunction(args, vararg, arrow, Box::new(body)))
     }
 
     fn visit_binary_expr(
@@ -28,6 +267,24 @@ impl Rewriter<String> for Folder {
         let left = self.visit_expr(*left)?;
         let right = self.visit_expr(*right)?;
 
+        if matches!(op.kind, TokenKind::And | TokenKind::Or) {
+            if let (Some(left_truthy), Some(_)) =
+                (Folder::truthiness(&left), Folder::truthiness(&right))
+            {
+                //same as at runtime: `or` gives first operand if it is truthy, `and` if it is not
+                let result = if left_truthy == (op.kind == TokenKind::Or) {
+                    left
+                } else {
+                    right
+                };
+                self.log.push(Optimization::ConstantFolded {
+                    position: op.position,
+                    result: Folder::literal_string(&result),
+                });
+                return Ok(result);
+            }
+        }
+
         let res: Expr = match (&left, &right) {
             (Expr::Number(a), Expr::Number(b)) => {
                 let na = a.get_number().unwrap();
@@ -41,7 +298,10 @@ impl Rewriter<String> for Folder {
                 }
 
                 let result = match &op.kind {
-                    TokenKind::Star => FoldResult::Ok(na*nb),
+                    TokenKind::Star => match na.checked_mul(nb) {
+                        Some(result) => FoldResult::Ok(result),
+                        None => FoldResult::Warning(format!("overflow when folding multiplication at [{}]", op.position))
+                    },
 
                     TokenKind::Slash => match na.checked_div(nb) {
                         Some(result) => FoldResult::Ok(result),
@@ -57,9 +317,15 @@ impl Rewriter<String> for Folder {
                         }
                     },
 
-                    TokenKind::Plus => FoldResult::Ok(na + nb),
+                    TokenKind::Plus => match na.checked_add(nb) {
+                        Some(result) => FoldResult::Ok(result),
+                        None => FoldResult::Warning(format!("overflow when folding addition at [{}]", op.position))
+                    },
 
-                    TokenKind::Minus => FoldResult::Ok(na - nb),
+                    TokenKind::Minus => match na.checked_sub(nb) {
+                        Some(result) => FoldResult::Ok(result),
+                        None => FoldResult::Warning(format!("overflow when folding subtraction at [{}]", op.position))
+                    },
 
                     TokenKind::CompareEquals => FoldResult::OkBool(na==nb),
                     TokenKind::CompareNotEquals => FoldResult::OkBool(na!=nb),
@@ -73,14 +339,26 @@ impl Rewriter<String> for Folder {
                 };
 
                 match result {
-                    FoldResult::Ok(number) => Expr::Number(Token {
-                        position: a.position,
-                        kind: TokenKind::Number(number),
-                    }),
-                    FoldResult::OkBool(b) => Expr::Bool(Token {
-                        position: a.position,
-                        kind: if b { TokenKind::True } else { TokenKind::False },
-                    }),
+                    FoldResult::Ok(number) => {
+                        self.log.push(Optimization::ConstantFolded {
+                            position: op.position,
+                            result: number.to_string(),
+                        });
+                        Expr::Number(Token {
+                            position: a.position,
+                            kind: TokenKind::Number(number),
+                        })
+                    }
+                    FoldResult::OkBool(b) => {
+                        self.log.push(Optimization::ConstantFolded {
+                            position: op.position,
+                            result: b.to_string(),
+                        });
+                        Expr::Bool(Token {
+                            position: a.position,
+                            kind: if b { TokenKind::True } else { TokenKind::False },
+                        })
+                    }
                     FoldResult::Warning(w) => {
                         eprintln!("{}", w);
                         Expr::Binary(op, Box::new(left), Box::new(right))
@@ -93,6 +371,10 @@ impl Rewriter<String> for Folder {
 
             (Expr::ConstString(s1), Expr::ConstString(s2)) if op.kind == TokenKind::Plus => {
                 let s = [s1.get_string().unwrap(), s2.get_string().unwrap()].join("");
+                self.log.push(Optimization::ConstantFolded {
+                    position: op.position,
+                    result: format!("{:?}", s),
+                });
                 Expr::ConstString(Token {
                     position: s1.position,
                     kind: TokenKind::ConstString(s),
@@ -104,4 +386,119 @@ impl Rewriter<String> for Folder {
 
         Ok(res)
     }
+
+    fn visit_unary_expr(&mut self, op: Token, arg: Box<Expr>) -> Result<Expr, String> {
+        let arg = self.visit_expr(*arg)?;
+
+        let folded = match (&op.kind, &arg) {
+            (TokenKind::Minus, Expr::Number(n)) => match n.get_number().unwrap().checked_neg() {
+                Some(number) => Some((TokenKind::Number(number), number.to_string())),
+                None => {
+                    eprintln!("overflow when folding negation at [{}]", op.position);
+                    None
+                }
+            },
+            (TokenKind::Tilde, Expr::Number(n)) => {
+                let number = !n.get_number().unwrap();
+                Some((TokenKind::Number(number), number.to_string()))
+            }
+            (TokenKind::Minus, Expr::FloatNumber(f)) => {
+                let number = -f.get_float().unwrap();
+                Some((
+                    TokenKind::FloatNumber(NotNan::new(number).unwrap()),
+                    number.to_string(),
+                ))
+            }
+            _ => None,
+        };
+
+        Ok(match folded {
+            Some((kind, result)) => {
+                self.log.push(Optimization::ConstantFolded {
+                    position: op.position,
+                    result,
+                });
+                let is_int = matches!(kind, TokenKind::Number(..));
+                let token = Token {
+                    position: op.position,
+                    kind,
+                };
+                if is_int {
+                    Expr::Number(token)
+                } else {
+                    Expr::FloatNumber(token)
+                }
+            }
+            None => Expr::Unary(op, Box::new(arg)),
+        })
+    }
+
+    fn visit_call_expr(&mut self, target: Box<Expr>, args: Vec<Expr>) -> Result<Expr, String> {
+        let target = self.visit_expr(*target)?;
+        Folder::check_callable(&target)?;
+        let args = args
+            .into_iter()
+            .map(|arg| self.visit_expr(arg))
+            .collect::<Result<Vec<Expr>, String>>()?;
+        Ok(Expr::Call(Box::new(target), args))
+    }
+
+    fn visit_partial_call_expr(
+        &mut self,
+        target: Box<Expr>,
+        args: Vec<Option<Expr>>,
+    ) -> Result<Expr, String> {
+        let target = self.visit_expr(*target)?;
+        Folder::check_callable(&target)?;
+        let args = args
+            .into_iter()
+            .map(|arg| arg.map(|arg| self.visit_expr(arg)).transpose())
+            .collect::<Result<Vec<Option<Expr>>, String>>()?;
+        Ok(Expr::PartialCall(Box::new(target), args))
+    }
+
+    fn visit_cond_expr(
+        &mut self,
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Option<Box<Expr>>,
+    ) -> Result<Expr, String> {
+        let condition = self.visit_expr(*condition)?;
+        let then_branch = self.visit_expr(*then_branch)?;
+        let else_branch = match else_branch {
+            Some(else_branch) => Some(self.visit_expr(*else_branch)?),
+            None => None,
+        };
+
+        //only literal conditions are eliminated, others are left for runtime
+        let condition_token = match &condition {
+            Expr::Bool(token) => token.clone(),
+            _ => {
+                return Ok(Expr::If(
+                    Box::new(condition),
+                    Box::new(then_branch),
+                    else_branch.map(Box::new),
+                ))
+            }
+        };
+
+        let keep_then_branch = condition_token.kind == TokenKind::True;
+
+        self.log.push(Optimization::BranchEliminated {
+            position: condition_token.position,
+            kept_then_branch: keep_then_branch,
+        });
+
+        Ok(if keep_then_branch {
+            then_branch
+        } else {
+            //missing else branch evaluates to Nothing, just like pass statement
+            else_branch.unwrap_or_else(|| {
+                Expr::SingleStatement(Box::new(Stmt::Pass(Token {
+                    position: condition_token.position,
+                    kind: TokenKind::Pass,
+                })))
+            })
+        })
+    }
 }
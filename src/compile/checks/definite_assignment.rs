@@ -0,0 +1,275 @@
+use super::lints::CapturedNames;
+use super::tree_visitor::Visitor;
+use crate::parsing::ast::{EnumVariant, Pattern, Program, Stmt};
+use crate::parsing::lexer::{Token, TokenKind};
+use crate::Expr;
+use std::collections::{HashMap, HashSet};
+
+/// variables of one block, declaration of variable is kept while it may still be unassigned
+type Scope = HashMap<String, Option<Token>>;
+
+/// checks that variables declared without value are assigned on every path before being read.
+/// Only reads inside function that declares variable are checked,
+/// variables used by nested functions are skipped as any call may assign them
+pub struct DefiniteAssignmentChecker {
+    /// scopes of functions being visited, innermost last
+    frames: Vec<Frame>,
+}
+
+#[derive(Default)]
+struct Frame {
+    scopes: Vec<Scope>,
+    /// names used by nested functions, for every scope
+    captured: Vec<HashSet<String>>,
+}
+
+impl DefiniteAssignmentChecker {
+    pub fn check(ast: &Program) -> Result<(), String> {
+        let mut checker = DefiniteAssignmentChecker {
+            frames: vec![Frame::default()],
+        };
+        checker.enter_scope(ast);
+        ast.iter().try_for_each(|s| checker.visit_stmt(s))
+    }
+
+    fn current_frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn enter_scope(&mut self, statements: &[Stmt]) {
+        let frame = self.current_frame();
+        frame.scopes.push(Scope::new());
+        frame.captured.push(CapturedNames::collect(statements));
+    }
+
+    fn exit_scope(&mut self) {
+        let frame = self.current_frame();
+        frame.scopes.pop();
+        frame.captured.pop();
+    }
+
+    fn declare(&mut self, name: &Token, assigned: bool) {
+        let frame = self.current_frame();
+        let name_str = name.get_string().unwrap();
+        let checked = !assigned && !frame.captured.last().unwrap().contains(name_str);
+        frame
+            .scopes
+            .last_mut()
+            .unwrap()
+            .insert(name_str.to_string(), checked.then(|| name.clone()));
+    }
+
+    /// finds innermost variable with given name declared in current function
+    fn lookup(&mut self, name: &str) -> Option<&mut Option<Token>> {
+        self.current_frame()
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    fn visit_function(
+        &mut self,
+        args: &[Token],
+        vararg: Option<&Token>,
+        body: &Expr,
+    ) -> Result<(), String> {
+        self.frames.push(Frame::default());
+        self.enter_scope(&[]);
+        //arguments are only declared to shadow outer names
+        for arg in args.iter().chain(vararg) {
+            self.declare(arg, true);
+        }
+        let result = self.visit_expr(body);
+        self.exit_scope();
+        self.frames.pop();
+        result
+    }
+
+    /// visits expression that may not be evaluated, assignments inside it are forgotten
+    fn visit_conditionally(&mut self, expr: &Expr) -> Result<(), String> {
+        let before = self.current_frame().scopes.clone();
+        self.visit_expr(expr)?;
+        self.current_frame().scopes = before;
+        Ok(())
+    }
+}
+
+impl Visitor<String> for DefiniteAssignmentChecker {
+    fn visit_var_stmt(&mut self, variable_name: &Token, rhs: Option<&Expr>) -> Result<(), String> {
+        if let Some(rhs) = rhs {
+            self.visit_expr(rhs)?;
+        }
+        self.declare(variable_name, rhs.is_some());
+        Ok(())
+    }
+
+    fn visit_destructuring_stmt(&mut self, pattern: &Pattern, value: &Expr) -> Result<(), String> {
+        self.visit_expr(value)?;
+        for name in pattern.names() {
+            self.declare(name, true);
+        }
+        Ok(())
+    }
+
+    fn visit_const_stmt(&mut self, name: &Token, value: &Expr) -> Result<(), String> {
+        self.visit_expr(value)?;
+        self.declare(name, true);
+        Ok(())
+    }
+
+    fn visit_assignment_stmt(&mut self, target: &Token, value: &Expr) -> Result<(), String> {
+        self.visit_expr(value)?;
+        if let Some(variable) = self.lookup(target.get_string().unwrap()) {
+            *variable = None;
+        }
+        Ok(())
+    }
+
+    fn visit_function_declaration_statement(
+        &mut self,
+        name: &Token,
+        args: &[Token],
+        vararg: Option<&Token>,
+        body: &Expr,
+    ) -> Result<(), String> {
+        self.declare(name, true);
+        self.visit_function(args, vararg, body)
+    }
+
+    fn visit_struct_declaration_statement(
+        &mut self,
+        name: &Token,
+        _fields: &[Token],
+    ) -> Result<(), String> {
+        self.declare(name, true);
+        Ok(())
+    }
+
+    fn visit_enum_declaration(
+        &mut self,
+        name: &Token,
+        _variants: &[EnumVariant],
+    ) -> Result<(), String> {
+        self.declare(name, true);
+        Ok(())
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        _module: &[Token],
+        name: &Token,
+        rename: Option<&Token>,
+    ) -> Result<(), String> {
+        self.declare(rename.unwrap_or(name), true);
+        Ok(())
+    }
+
+    fn visit_impl_block(&mut self, _name: &Token, implementations: &[Stmt]) -> Result<(), String> {
+        for implementation in implementations {
+            if let Stmt::FunctionDeclaration {
+                name,
+                args,
+                vararg,
+                body,
+            } = implementation
+            {
+                self.visit_method(name, args, vararg.as_ref(), body)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_method(
+        &mut self,
+        _name: &Token,
+        args: &[Token],
+        vararg: Option<&Token>,
+        body: &Expr,
+    ) -> Result<(), String> {
+        self.visit_function(args, vararg, body)
+    }
+
+    fn visit_variable_expr(&mut self, variable_name: &Token) -> Result<(), String> {
+        match self.lookup(variable_name.get_string().unwrap()) {
+            Some(Some(declaration)) => Err(format!(
+                "variable {} [{}] may be read before it is assigned, declared without value at [{}]",
+                variable_name.get_string().unwrap(),
+                variable_name.position,
+                declaration.position
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn visit_binary_expr(&mut self, op: &Token, left: &Expr, right: &Expr) -> Result<(), String> {
+        self.visit_expr(left)?;
+        if matches!(op.kind, TokenKind::And | TokenKind::Or) {
+            self.visit_conditionally(right)
+        } else {
+            self.visit_expr(right)
+        }
+    }
+
+    fn visit_cond_expr(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: Option<&Expr>,
+    ) -> Result<(), String> {
+        self.visit_expr(condition)?;
+        let before = self.current_frame().scopes.clone();
+
+        self.visit_expr(then_branch)?;
+        let after_then = std::mem::replace(&mut self.current_frame().scopes, before);
+
+        if let Some(else_branch) = else_branch {
+            self.visit_expr(else_branch)?;
+        }
+
+        //variable stays unassigned unless both branches assigned it
+        for (scope, then_scope) in self.current_frame().scopes.iter_mut().zip(after_then) {
+            for (name, declaration) in then_scope {
+                if let (Some(declaration), Some(variable)) = (declaration, scope.get_mut(&name)) {
+                    variable.get_or_insert(declaration);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// body may be interrupted by error at any point, so its assignments are not relied on
+    fn visit_try_expr(
+        &mut self,
+        _try_token: &Token,
+        body: &Expr,
+        handler: &Expr,
+    ) -> Result<(), String> {
+        self.visit_conditionally(body)?;
+        self.visit_expr(handler)
+    }
+
+    fn visit_block(
+        &mut self,
+        _start_token: &Token,
+        _end_token: &Token,
+        containing_statements: &[Stmt],
+    ) -> Result<(), String> {
+        self.enter_scope(containing_statements);
+        let result = containing_statements
+            .iter()
+            .try_for_each(|stmt| self.visit_stmt(stmt));
+        self.exit_scope();
+        result
+    }
+
+    fn visit_anon_function_expr(
+        &mut self,
+        args: &[Token],
+        vararg: Option<&Token>,
+        _arrow: &Token,
+        body: &Expr,
+    ) -> Result<(), String> {
+        self.visit_function(args, vararg, body)
+    }
+}
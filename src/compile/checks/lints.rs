@@ -0,0 +1,695 @@
+use crate::compile::checks::tree_visitor::Visitor;
+use crate::parsing::ast::{EnumVariant, Pattern, Program, Stmt};
+use crate::parsing::lexer::{Index, Token, TokenKind};
+use crate::Expr;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// suspicious but valid code found by [`Linter`], reported without failing compilation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub position: Index,
+    pub message: String,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [{}]", self.message, self.position)
+    }
+}
+
+/// selects lints to run, noisy lints are opt-in
+#[derive(Clone, Debug, Default)]
+pub struct LintConfig {
+    /// `==` and `!=` between operands of statically known different types
+    pub disjoint_comparison: bool,
+    /// value assigned to local variable is overwritten or goes out of scope before being read
+    pub dead_store: bool,
+    /// declarations reusing name of one of these builtins, empty unless filled by caller
+    pub builtins: HashSet<String>,
+    /// builtins that may be shadowed without warning
+    pub allowed_shadowing: HashSet<String>,
+    /// condition of `if` that is known to always be true or always be false
+    pub constant_condition: bool,
+    /// expression statement that has no side effects and whose value is not used
+    pub discarded_value: bool,
+}
+
+impl LintConfig {
+    /// enables every lint, builtins to check shadowing against are still left to caller
+    pub fn all() -> Self {
+        LintConfig {
+            disjoint_comparison: true,
+            dead_store: true,
+            constant_condition: true,
+            discarded_value: true,
+            ..Default::default()
+        }
+    }
+}
+
+pub(super) struct Linter<'c> {
+    config: &'c LintConfig,
+    warnings: Vec<Warning>,
+}
+
+impl<'c> Linter<'c> {
+    pub fn lint(program: &Program, config: &'c LintConfig) -> Vec<Warning> {
+        let mut linter = Linter {
+            config,
+            warnings: vec![],
+        };
+        linter.visit_statements(program);
+        if config.dead_store {
+            linter.warnings.append(&mut DeadStores::find(program));
+        }
+        linter.warnings
+    }
+
+    fn warn(&mut self, position: Index, message: String) {
+        self.warnings.push(Warning { position, message })
+    }
+
+    fn check_shadowing(&mut self, name: &Token) {
+        let name_str = name.get_string().unwrap();
+        if self.config.builtins.contains(name_str)
+            && !self.config.allowed_shadowing.contains(name_str)
+        {
+            self.warn(
+                name.position,
+                format!("{} shadows builtin with the same name", name_str),
+            );
+        }
+    }
+
+    fn check_arguments_shadowing(&mut self, args: &[Token], vararg: Option<&Token>) {
+        for arg in args.iter().chain(vararg) {
+            self.check_shadowing(arg);
+        }
+    }
+
+    /// visits statements of program or block, warning on pure expression statements.
+    /// Last statement is skipped as it gives value of the whole block
+    fn visit_statements(&mut self, statements: &[Stmt]) {
+        for (i, stmt) in statements.iter().enumerate() {
+            let is_discarded = self.config.discarded_value && i + 1 < statements.len();
+            if let (true, Stmt::Expression(expr)) = (is_discarded, stmt) {
+                if let Some(position) = Linter::pure_expression_position(expr) {
+                    self.warn(
+                        position,
+                        "expression has no effect and its value is discarded".to_string(),
+                    );
+                }
+            }
+            let _ = self.visit_stmt(stmt);
+        }
+    }
+
+    /// literal or operators applied to literals only
+    fn is_constant(expr: &Expr) -> bool {
+        match expr {
+            Expr::Number(..) | Expr::FloatNumber(..) | Expr::Bool(..) | Expr::ConstString(..) => {
+                true
+            }
+            Expr::Unary(_, arg) => Linter::is_constant(arg),
+            Expr::Binary(_, left, right) => Linter::is_constant(left) && Linter::is_constant(right),
+            _ => false,
+        }
+    }
+
+    /// position of leftmost token if evaluating expression has no side effects.
+    /// Calls are never pure, and neither are binary operators on variables
+    /// as they may call overloaded operator methods
+    fn pure_expression_position(expr: &Expr) -> Option<Index> {
+        fn all_pure<'e>(mut exprs: impl Iterator<Item = &'e Expr>) -> bool {
+            exprs.all(|expr| Linter::pure_expression_position(expr).is_some())
+        }
+        match expr {
+            Expr::Number(t)
+            | Expr::FloatNumber(t)
+            | Expr::Bool(t)
+            | Expr::Name(t)
+            | Expr::ConstString(t) => Some(t.position),
+            Expr::Unary(op, arg) => Linter::pure_expression_position(arg).map(|_| op.position),
+            Expr::Binary(..) if !Linter::is_constant(expr) => None,
+            Expr::Binary(_, left, _) => Linter::pure_expression_position(left),
+            Expr::ListLiteral(t, items) | Expr::TupleLiteral(t, items) => {
+                all_pure(items.iter()).then_some(t.position)
+            }
+            Expr::MapLiteral(t, entries) => {
+                all_pure(entries.iter().flat_map(|(k, v)| [k, v])).then_some(t.position)
+            }
+            Expr::AnonFunction(_, _, arrow, _) => Some(arrow.position),
+            Expr::PropertyAccess(target, _)
+            | Expr::PropertyTest(target, _)
+            | Expr::Cast(target, _, _) => Linter::pure_expression_position(target),
+            Expr::Index(target, _, index) => {
+                let position = Linter::pure_expression_position(target)?;
+                Linter::pure_expression_position(index).map(|_| position)
+            }
+            Expr::Slice(target, _, start, end) => {
+                let position = Linter::pure_expression_position(target)?;
+                all_pure(start.iter().chain(end).map(Box::as_ref)).then_some(position)
+            }
+            _ => None,
+        }
+    }
+
+    /// value of condition if it does not depend on program state, together with position
+    /// of token deciding it
+    fn constant_condition(expr: &Expr) -> Option<(bool, Index)> {
+        match expr {
+            Expr::Bool(t) => Some((t.kind == TokenKind::True, t.position)),
+            Expr::Unary(op, arg) if op.kind == TokenKind::Not => {
+                Linter::constant_condition(arg).map(|(value, _)| (!value, op.position))
+            }
+            Expr::Binary(op, left, right) => {
+                let value = match (op.kind.clone(), left.as_ref(), right.as_ref()) {
+                    (TokenKind::And, left, right) => {
+                        let left = Linter::constant_condition(left).map(|(value, _)| value);
+                        let right = Linter::constant_condition(right).map(|(value, _)| value);
+                        match (left, right) {
+                            (Some(false), _) | (_, Some(false)) => Some(false),
+                            (Some(true), Some(true)) => Some(true),
+                            _ => None,
+                        }
+                    }
+                    (TokenKind::Or, left, right) => {
+                        let left = Linter::constant_condition(left).map(|(value, _)| value);
+                        let right = Linter::constant_condition(right).map(|(value, _)| value);
+                        match (left, right) {
+                            (Some(true), _) | (_, Some(true)) => Some(true),
+                            (Some(false), Some(false)) => Some(false),
+                            _ => None,
+                        }
+                    }
+                    //variable compared with itself
+                    (kind, Expr::Name(a), Expr::Name(b)) if a.kind == b.kind => match kind {
+                        TokenKind::CompareEquals
+                        | TokenKind::CompareGreaterEqual
+                        | TokenKind::CompareLessEqual => Some(true),
+                        TokenKind::CompareNotEquals
+                        | TokenKind::CompareGreater
+                        | TokenKind::CompareLess => Some(false),
+                        _ => None,
+                    },
+                    (kind, Expr::Number(a), Expr::Number(b)) => {
+                        let (a, b) = (a.get_number().unwrap(), b.get_number().unwrap());
+                        match kind {
+                            TokenKind::CompareEquals => Some(a == b),
+                            TokenKind::CompareNotEquals => Some(a != b),
+                            TokenKind::CompareGreater => Some(a > b),
+                            TokenKind::CompareGreaterEqual => Some(a >= b),
+                            TokenKind::CompareLess => Some(a < b),
+                            TokenKind::CompareLessEqual => Some(a <= b),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                value.map(|value| (value, op.position))
+            }
+            _ => None,
+        }
+    }
+
+    /// type of expression if it can be told without running program
+    fn static_type(expr: &Expr) -> Option<&str> {
+        match expr {
+            Expr::Number(..) => Some("Int"),
+            Expr::FloatNumber(..) => Some("Float"),
+            Expr::Bool(..) => Some("Bool"),
+            Expr::ConstString(..) => Some("String"),
+            Expr::MapLiteral(..) => Some("Map"),
+            Expr::ListLiteral(..) | Expr::TupleLiteral(..) => Some("List"),
+            Expr::AnonFunction(..) => Some("Function"),
+            Expr::Cast(_, _, target) => target.get_string(),
+            Expr::Unary(op, _) if op.kind == TokenKind::Not => Some("Bool"),
+            Expr::Unary(op, _) if op.kind == TokenKind::Tilde => Some("Int"),
+            Expr::Binary(op, ..) => match op.kind {
+                TokenKind::CompareEquals
+                | TokenKind::CompareNotEquals
+                | TokenKind::CompareGreater
+                | TokenKind::CompareGreaterEqual
+                | TokenKind::CompareLess
+                | TokenKind::CompareLessEqual => Some("Bool"),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<'c> Visitor<()> for Linter<'c> {
+    fn visit_cond_expr(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: Option<&Expr>,
+    ) -> Result<(), ()> {
+        if self.config.constant_condition {
+            if let Some((value, position)) = Linter::constant_condition(condition) {
+                self.warn(position, format!("condition is always {}", value));
+            }
+        }
+
+        self.visit_expr(condition)?;
+        self.visit_expr(then_branch)?;
+        else_branch.map_or(Ok(()), |else_branch| self.visit_expr(else_branch))
+    }
+
+    fn visit_var_stmt(&mut self, variable_name: &Token, rhs: Option<&Expr>) -> Result<(), ()> {
+        self.check_shadowing(variable_name);
+        rhs.map_or(Ok(()), |rhs| self.visit_expr(rhs))
+    }
+
+    fn visit_destructuring_stmt(&mut self, pattern: &Pattern, value: &Expr) -> Result<(), ()> {
+        for name in pattern.names() {
+            self.check_shadowing(name);
+        }
+        self.visit_expr(value)
+    }
+
+    fn visit_const_stmt(&mut self, name: &Token, value: &Expr) -> Result<(), ()> {
+        self.check_shadowing(name);
+        self.visit_expr(value)
+    }
+
+    fn visit_function_declaration_statement(
+        &mut self,
+        name: &Token,
+        args: &[Token],
+        vararg: Option<&Token>,
+        body: &Expr,
+    ) -> Result<(), ()> {
+        self.check_shadowing(name);
+        self.check_arguments_shadowing(args, vararg);
+        self.visit_expr(body)
+    }
+
+    fn visit_impl_block(&mut self, _name: &Token, implementations: &[Stmt]) -> Result<(), ()> {
+        for implementation in implementations {
+            if let Stmt::FunctionDeclaration {
+                name,
+                args,
+                vararg,
+                body,
+            } = implementation
+            {
+                self.visit_method(name, args, vararg.as_ref(), body)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// methods are looked up on values, so only arguments may shadow builtins
+    fn visit_method(
+        &mut self,
+        _name: &Token,
+        args: &[Token],
+        vararg: Option<&Token>,
+        body: &Expr,
+    ) -> Result<(), ()> {
+        self.check_arguments_shadowing(args, vararg);
+        self.visit_expr(body)
+    }
+
+    fn visit_struct_declaration_statement(
+        &mut self,
+        name: &Token,
+        _fields: &[Token],
+    ) -> Result<(), ()> {
+        self.check_shadowing(name);
+        Ok(())
+    }
+
+    fn visit_enum_declaration(
+        &mut self,
+        name: &Token,
+        _variants: &[EnumVariant],
+    ) -> Result<(), ()> {
+        self.check_shadowing(name);
+        Ok(())
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        _module: &[Token],
+        name: &Token,
+        rename: Option<&Token>,
+    ) -> Result<(), ()> {
+        self.check_shadowing(rename.unwrap_or(name));
+        Ok(())
+    }
+
+    fn visit_anon_function_expr(
+        &mut self,
+        args: &[Token],
+        vararg: Option<&Token>,
+        _arrow: &Token,
+        body: &Expr,
+    ) -> Result<(), ()> {
+        self.check_arguments_shadowing(args, vararg);
+        self.visit_expr(body)
+    }
+
+    fn visit_block(
+        &mut self,
+        _start_token: &Token,
+        _end_token: &Token,
+        containing_statements: &[Stmt],
+    ) -> Result<(), ()> {
+        self.visit_statements(containing_statements);
+        Ok(())
+    }
+
+    fn visit_binary_expr(&mut self, op: &Token, left: &Expr, right: &Expr) -> Result<(), ()> {
+        let is_equality = matches!(
+            op.kind,
+            TokenKind::CompareEquals | TokenKind::CompareNotEquals
+        );
+
+        if self.config.disjoint_comparison && is_equality {
+            if let (Some(left_type), Some(right_type)) =
+                (Linter::static_type(left), Linter::static_type(right))
+            {
+                //numbers are converted to common type before comparing
+                let both_numbers = [left_type, right_type]
+                    .iter()
+                    .all(|t| matches!(*t, "Int" | "Float"));
+                if left_type != right_type && !both_numbers {
+                    self.warn(
+                        op.position,
+                        format!(
+                            "comparison between {} and {} is always {}",
+                            left_type,
+                            right_type,
+                            op.kind == TokenKind::CompareNotEquals
+                        ),
+                    );
+                }
+            }
+        }
+
+        self.visit_expr(left)?;
+        self.visit_expr(right)
+    }
+}
+
+/// local variable tracked by [`DeadStores`]
+struct LocalVariable {
+    /// position of the last assignment that was not read yet
+    unread_store: Option<Index>,
+    /// used by nested function which may run at any moment, so stores are not checked
+    escaped: bool,
+}
+
+struct Scope {
+    /// conditionally evaluated regions (branches, right side of `and`/`or`) around scope.
+    /// Store from a deeper region may not run, so it does not kill previous one
+    conditional_depth: usize,
+    variables: HashMap<String, LocalVariable>,
+    /// names used by functions nested in scope, they may be declared before variable
+    captured: HashSet<String>,
+}
+
+/// collects names read or assigned inside nested functions
+#[derive(Default)]
+pub(super) struct CapturedNames {
+    names: HashSet<String>,
+    function_depth: usize,
+}
+
+impl CapturedNames {
+    pub(super) fn collect(statements: &[Stmt]) -> HashSet<String> {
+        let mut collector = CapturedNames::default();
+        for stmt in statements {
+            let _ = collector.visit_stmt(stmt);
+        }
+        collector.names
+    }
+
+    pub(super) fn collect_in_expr(expr: &Expr) -> HashSet<String> {
+        let mut collector = CapturedNames::default();
+        let _ = collector.visit_expr(expr);
+        collector.names
+    }
+
+    fn add(&mut self, name: &Token) {
+        if self.function_depth > 0 {
+            self.names.insert(name.get_string().unwrap().to_string());
+        }
+    }
+
+    fn visit_function(&mut self, body: &Expr) {
+        self.function_depth += 1;
+        let _ = self.visit_expr(body);
+        self.function_depth -= 1;
+    }
+}
+
+impl Visitor<()> for CapturedNames {
+    fn visit_assignment_stmt(&mut self, target: &Token, value: &Expr) -> Result<(), ()> {
+        self.add(target);
+        self.visit_expr(value)
+    }
+
+    fn visit_function_declaration_statement(
+        &mut self,
+        _name: &Token,
+        _args: &[Token],
+        _vararg: Option<&Token>,
+        body: &Expr,
+    ) -> Result<(), ()> {
+        self.visit_function(body);
+        Ok(())
+    }
+
+    fn visit_variable_expr(&mut self, variable_name: &Token) -> Result<(), ()> {
+        self.add(variable_name);
+        Ok(())
+    }
+
+    fn visit_anon_function_expr(
+        &mut self,
+        _args: &[Token],
+        _vararg: Option<&Token>,
+        _arrow: &Token,
+        body: &Expr,
+    ) -> Result<(), ()> {
+        self.visit_function(body);
+        Ok(())
+    }
+}
+
+/// finds values assigned to local variables that are overwritten or go out of scope
+/// before being read. Globals are not checked as any call may read them
+struct DeadStores {
+    /// scopes of functions being visited, innermost last
+    frames: Vec<Vec<Scope>>,
+    conditional_depth: usize,
+    warnings: Vec<Warning>,
+}
+
+impl DeadStores {
+    fn find(program: &Program) -> Vec<Warning> {
+        let mut finder = DeadStores {
+            frames: vec![vec![]], //top level declares globals, so it has no scope
+            conditional_depth: 0,
+            warnings: vec![],
+        };
+        for stmt in program {
+            let _ = finder.visit_stmt(stmt);
+        }
+        finder
+            .warnings
+            .sort_by_key(|w| (w.position.0, w.position.1));
+        finder.warnings
+    }
+
+    fn current_frame(&mut self) -> &mut Vec<Scope> {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn declare(&mut self, name: &Token, store: Option<Index>) {
+        if let Some(scope) = self.current_frame().last_mut() {
+            let name = name.get_string().unwrap();
+            let escaped = scope.captured.contains(name);
+            scope.variables.insert(
+                name.to_string(),
+                LocalVariable {
+                    unread_store: store,
+                    escaped,
+                },
+            );
+        }
+    }
+
+    /// innermost variable of current function with given name together with
+    /// conditional depth of its scope. Variables of outer functions used here are escaped
+    fn lookup(&mut self, name: &Token) -> Option<(&mut LocalVariable, usize)> {
+        let name = name.get_string().unwrap();
+        self.current_frame().iter_mut().rev().find_map(|scope| {
+            let depth = scope.conditional_depth;
+            scope
+                .variables
+                .get_mut(name)
+                .map(|variable| (variable, depth))
+        })
+    }
+
+    fn read(&mut self, name: &Token) {
+        if let Some((variable, _)) = self.lookup(name) {
+            variable.unread_store = None;
+        }
+    }
+
+    fn store(&mut self, name: &Token) {
+        let current_depth = self.conditional_depth;
+        let overwritten = match self.lookup(name) {
+            Some((variable, depth)) if !variable.escaped && depth == current_depth => {
+                variable.unread_store.replace(name.position)
+            }
+            _ => None,
+        };
+        if let Some(position) = overwritten {
+            let name = name.get_string().unwrap();
+            self.warn_unread(position, name, "is overwritten before being read");
+        }
+    }
+
+    fn warn_unread(&mut self, position: Index, name: &str, reason: &str) {
+        self.warnings.push(Warning {
+            position,
+            message: format!("value assigned to {} {}", name, reason),
+        })
+    }
+
+    fn enter_scope(&mut self, captured: HashSet<String>) {
+        let conditional_depth = self.conditional_depth;
+        self.current_frame().push(Scope {
+            conditional_depth,
+            variables: HashMap::new(),
+            captured,
+        });
+    }
+
+    fn exit_scope(&mut self) {
+        let scope = self.current_frame().pop().unwrap();
+        for (name, variable) in scope.variables {
+            if let (Some(position), false) = (variable.unread_store, variable.escaped) {
+                self.warn_unread(position, &name, "is never read");
+            }
+        }
+    }
+
+    fn visit_function(&mut self, args: &[Token], vararg: Option<&Token>, body: &Expr) {
+        let outer_depth = std::mem::replace(&mut self.conditional_depth, 0);
+        self.frames.push(vec![]);
+        //arguments used by closures in body are read through them
+        self.enter_scope(CapturedNames::collect_in_expr(body));
+        //arguments are only declared to shadow outer names
+        for arg in args.iter().chain(vararg) {
+            self.declare(arg, None);
+        }
+        let _ = self.visit_expr(body);
+        self.exit_scope();
+        self.frames.pop();
+        self.conditional_depth = outer_depth;
+    }
+
+    fn visit_conditionally(&mut self, expr: &Expr) {
+        self.conditional_depth += 1;
+        let _ = self.visit_expr(expr);
+        self.conditional_depth -= 1;
+    }
+}
+
+impl Visitor<()> for DeadStores {
+    fn visit_var_stmt(&mut self, variable_name: &Token, rhs: Option<&Expr>) -> Result<(), ()> {
+        if let Some(rhs) = rhs {
+            self.visit_expr(rhs)?;
+        }
+        self.declare(variable_name, rhs.map(|_| variable_name.position));
+        Ok(())
+    }
+
+    fn visit_destructuring_stmt(&mut self, pattern: &Pattern, value: &Expr) -> Result<(), ()> {
+        self.visit_expr(value)?;
+        for name in pattern.names() {
+            self.declare(name, None);
+        }
+        Ok(())
+    }
+
+    fn visit_assignment_stmt(&mut self, target: &Token, value: &Expr) -> Result<(), ()> {
+        self.visit_expr(value)?;
+        self.store(target);
+        Ok(())
+    }
+
+    fn visit_function_declaration_statement(
+        &mut self,
+        _name: &Token,
+        args: &[Token],
+        vararg: Option<&Token>,
+        body: &Expr,
+    ) -> Result<(), ()> {
+        self.visit_function(args, vararg, body);
+        Ok(())
+    }
+
+    fn visit_variable_expr(&mut self, variable_name: &Token) -> Result<(), ()> {
+        self.read(variable_name);
+        Ok(())
+    }
+
+    fn visit_binary_expr(&mut self, op: &Token, left: &Expr, right: &Expr) -> Result<(), ()> {
+        self.visit_expr(left)?;
+        if matches!(op.kind, TokenKind::And | TokenKind::Or) {
+            self.visit_conditionally(right);
+            Ok(())
+        } else {
+            self.visit_expr(right)
+        }
+    }
+
+    fn visit_cond_expr(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: Option<&Expr>,
+    ) -> Result<(), ()> {
+        self.visit_expr(condition)?;
+        self.visit_conditionally(then_branch);
+        if let Some(else_branch) = else_branch {
+            self.visit_conditionally(else_branch);
+        }
+        Ok(())
+    }
+
+    fn visit_block(
+        &mut self,
+        _start_token: &Token,
+        _end_token: &Token,
+        containing_statements: &[Stmt],
+    ) -> Result<(), ()> {
+        self.enter_scope(CapturedNames::collect(containing_statements));
+        for stmt in containing_statements {
+            self.visit_stmt(stmt)?;
+        }
+        self.exit_scope();
+        Ok(())
+    }
+
+    fn visit_anon_function_expr(
+        &mut self,
+        args: &[Token],
+        vararg: Option<&Token>,
+        _arrow: &Token,
+        body: &Expr,
+    ) -> Result<(), ()> {
+        self.visit_function(args, vararg, body);
+        Ok(())
+    }
+}
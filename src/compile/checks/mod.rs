@@ -1,18 +1,26 @@
 mod constant_folding;
+mod definite_assignment;
 mod expression_lift;
+mod lints;
 mod name_definition_check;
+mod optimization_check;
 mod tree_rewriter;
 mod tree_visitor;
 mod variable_annotation_generation;
 
 use crate::compile::checks::constant_folding::Folder;
+use crate::compile::checks::definite_assignment::DefiniteAssignmentChecker;
 use crate::compile::checks::expression_lift::ExpressionLifter;
+use crate::compile::checks::lints::Linter;
 use crate::compile::checks::name_definition_check::NameRedefinitionChecker;
 use crate::compile::checks::variable_annotation_generation::AnnotationGenerator;
 use crate::parsing::ast::Program;
-use crate::parsing::lexer::Token;
+use crate::parsing::lexer::{Index, Token};
 use indexmap::{IndexMap, IndexSet};
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+pub use lints::{LintConfig, Warning};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum VariableType {
@@ -60,14 +68,531 @@ impl Annotations {
     pub fn get_closure_scope(&self, closure_id: &Token) -> Option<&IndexSet<String>> {
         self.closed_names_table.get(closure_id)
     }
+
+    /// human-readable listing of all scopes ordered by position in source, for debugging
+    #[allow(dead_code)]
+    pub fn dump(&self) -> String {
+        fn scope_name(token: &Token) -> String {
+            match token.get_string() {
+                Some(name) => format!("{} [{}]", name, token.position),
+                None => format!("{} [{}]", token.kind, token.position),
+            }
+        }
+
+        let mut blocks = self.block_symbol_table.iter().collect::<Vec<_>>();
+        blocks.sort_by_key(|(token, _)| (token.position.0, token.position.1));
+        let mut closures = self.closed_names_table.iter().collect::<Vec<_>>();
+        closures.sort_by_key(|(token, _)| (token.position.0, token.position.1));
+
+        let mut result = String::new();
+        for (token, variables) in blocks {
+            result.push_str(&format!("block {}\n", scope_name(token)));
+            for (name, variable_type) in variables {
+                result.push_str(&format!("    {}: {:?}\n", name, variable_type));
+            }
+        }
+        for (token, names) in closures {
+            result.push_str(&format!("closure {}\n", scope_name(token)));
+            for name in names {
+                result.push_str(&format!("    {}\n", name));
+            }
+        }
+        result
+    }
+}
+
+/// rewrite performed by optimization passes, reported by [`check_optimize_verbose`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Optimization {
+    ConstantFolded {
+        position: Index,
+        result: String,
+    },
+    BranchEliminated {
+        position: Index,
+        kept_then_branch: bool,
+    },
+    ConstantSubstituted {
+        position: Index,
+        name: String,
+    },
+}
+
+impl Display for Optimization {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Optimization::ConstantFolded { position, result } => {
+                write!(
+                    f,
+                    "folded constant expression into {} [{}]",
+                    result, position
+                )
+            }
+            Optimization::BranchEliminated {
+                position,
+                kept_then_branch,
+            } => write!(
+                f,
+                "eliminated {} branch of condition with constant value [{}]",
+                if *kept_then_branch { "else" } else { "then" },
+                position
+            ),
+            Optimization::ConstantSubstituted { position, name } => {
+                write!(f, "substituted value of constant {} [{}]", name, position)
+            }
+        }
+    }
 }
 
 pub fn check_optimize(tree: Program) -> Result<(Program, Annotations), String> {
+    check_optimize_verbose(tree).map(|(tree, annotations, _log)| (tree, annotations))
+}
+
+/// same as [`check_optimize`], but also returns log of applied optimizations
+pub fn check_optimize_verbose(
+    tree: Program,
+) -> Result<(Program, Annotations, Vec<Optimization>), String> {
     NameRedefinitionChecker::check(&tree)?;
+    DefiniteAssignmentChecker::check(&tree)?;
     let tree = ExpressionLifter::optimize(tree)?;
     let mut annotations = Annotations::new();
     AnnotationGenerator::generate_annotations(&tree, &mut annotations)?;
-    let tree = Folder::fold_constants(tree)?;
+    let (tree, log) = Folder::fold_constants(tree)?;
+
+    Ok((tree, annotations, log))
+}
+
+/// reports suspicious code enabled in config, never fails
+pub fn lint(tree: &Program, config: &LintConfig) -> Vec<Warning> {
+    Linter::lint(tree, config)
+}
+
+#[cfg(test)]
+mod test {
+    use super::optimization_check::verify_optimization;
+    use super::tree_rewriter::Rewriter;
+    use super::variable_annotation_generation::AnnotationGenerator;
+    use super::{
+        check_optimize, check_optimize_verbose, lint, Annotations, LintConfig, Optimization,
+    };
+    use crate::parsing::ast::{Program, Stmt};
+    use crate::parsing::lexer::{tokenize, Index, Token, TokenKind};
+    use crate::parsing::parser::program_parser;
+    use crate::Expr;
+
+    fn parse(source: &str) -> Program {
+        let tokens = tokenize(source).unwrap();
+        let tokens = tokens.iter().collect::<Vec<_>>();
+        program_parser::program(&tokens).unwrap()
+    }
+
+    #[test]
+    fn verbose_check_should_log_folded_constants() {
+        let (_, _, log) = check_optimize_verbose(parse("var a = 2 + 3")).unwrap();
+
+        assert_eq!(
+            log,
+            vec![Optimization::ConstantFolded {
+                position: Index(1, 11, 10),
+                result: "5".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn logic_operators_should_fold_to_operand() {
+        let folded = |source: &str| {
+            let (program, _, log) = check_optimize_verbose(parse(source)).unwrap();
+            match (&program[0], log.as_slice()) {
+                (
+                    Stmt::VarDeclaration(_, Some(value)),
+                    [Optimization::ConstantFolded { result, .. }],
+                ) => (value.clone(), result.clone()),
+                other => panic!("expected single folded declaration, got {:?}", other),
+            }
+        };
+
+        let (value, result) = folded("var a = 1 or 2");
+        assert!(matches!(value, Expr::Number(n) if n.get_number() == Some(1)));
+        assert_eq!(result, "1");
+        assert_eq!(folded("var a = 1 and 2").1, "2");
+        assert_eq!(folded("var a = false or 2.5").1, "2.5");
+        assert_eq!(folded("var a = \"\" and false").1, "false");
+        assert_eq!(folded("var a = false and 1").1, "false");
+    }
+
+    #[test]
+    fn constants_used_in_arithmetic_should_be_folded_away() {
+        let (program, _, log) =
+            check_optimize_verbose(parse("const N = 4\nvar a = N * 2")).unwrap();
+
+        assert_eq!(
+            log,
+            vec![
+                Optimization::ConstantSubstituted {
+                    position: Index(2, 9, 20),
+                    name: "N".to_string()
+                },
+                Optimization::ConstantFolded {
+                    position: Index(2, 11, 22),
+                    result: "8".to_string()
+                }
+            ]
+        );
+        assert!(matches!(
+            &program[1],
+            Stmt::VarDeclaration(_, Some(Expr::Number(n))) if n.get_number() == Some(8)
+        ));
+    }
+
+    #[test]
+    fn constants_shadowed_by_locals_should_not_be_substituted() {
+        let source = "const N = 4\ndef f(N) = N * 2\ndef g =\n    var N = 1\n    N\n";
+        let (_, _, log) = check_optimize_verbose(parse(source)).unwrap();
+
+        assert!(log.is_empty(), "{:?}", log);
+    }
+
+    #[test]
+    fn verbose_check_should_log_eliminated_branches() {
+        let (_, _, log) = check_optimize_verbose(parse("var a = if 1 == 2 3 else 4")).unwrap();
+
+        assert_eq!(
+            log,
+            vec![
+                Optimization::ConstantFolded {
+                    position: Index(1, 14, 13),
+                    result: "false".to_string()
+                },
+                Optimization::BranchEliminated {
+                    position: Index(1, 12, 11),
+                    kept_then_branch: false
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn annotations_dump_should_list_boxed_and_closed_names() {
+        let source =
+            "def outer(a) =\n    var counter = a\n    def inner = counter + a\n    inner\n";
+        let (_, annotations) = check_optimize(parse(source)).unwrap();
+        let dump = annotations.dump();
+
+        assert!(dump.contains("block outer [1:5]\n    a: Boxed\n"));
+        assert!(dump.contains("block BeginBlock [2:5]\n    counter: Boxed\n    inner: Normal\n"));
+        assert!(dump.contains("closure inner [3:9]\n    counter\n    a\n"));
+    }
+
+    fn lint_messages(source: &str, config: &LintConfig) -> Vec<String> {
+        lint(&parse(source), config)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    fn disjoint_comparison_config() -> LintConfig {
+        LintConfig {
+            disjoint_comparison: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn comparison_of_disjoint_types_should_warn() {
+        let warnings = lint(&parse("var a = 1 == \"a\""), &disjoint_comparison_config());
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].position, Index(1, 11, 10));
+        assert_eq!(
+            warnings[0].message,
+            "comparison between Int and String is always false"
+        );
+    }
+
+    #[test]
+    fn comparison_of_same_types_should_not_warn() {
+        let config = disjoint_comparison_config();
+        assert!(lint_messages("var a = 1 == 2", &config).is_empty());
+        assert!(lint_messages("def f(x) = x != \"a\"", &config).is_empty());
+    }
+
+    #[test]
+    fn comparison_of_int_and_float_should_not_warn() {
+        let config = disjoint_comparison_config();
+        assert!(lint_messages("var a = 1 == 1.0", &config).is_empty());
+        assert!(lint_messages("var a = 2.5 != 2", &config).is_empty());
+    }
+
+    #[test]
+    fn disjoint_comparison_lint_is_opt_in() {
+        assert!(lint_messages("var a = 1 == \"a\"", &LintConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn literal_condition_should_warn() {
+        let config = LintConfig {
+            constant_condition: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            lint_messages("var a = if true 1 else 2", &config),
+            vec!["condition is always true [1:12]"]
+        );
+        assert_eq!(
+            lint_messages(
+                "def f(x) =\n    if not (1 > 2) and false\n        x",
+                &config
+            ),
+            vec!["condition is always false [2:20]"]
+        );
+    }
+
+    #[test]
+    fn variable_compared_with_itself_should_warn() {
+        let config = LintConfig {
+            constant_condition: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            lint_messages("def f(x) = if x != x 1 else 2", &config),
+            vec!["condition is always false [1:17]"]
+        );
+    }
+
+    #[test]
+    fn condition_depending_on_state_should_not_warn() {
+        let config = LintConfig {
+            constant_condition: true,
+            ..Default::default()
+        };
+        let source = "def f(x, y) = if x == y or false 1 else 2";
+        assert!(lint_messages(source, &config).is_empty());
+        assert!(lint_messages("var a = if true 1 else 2", &LintConfig::default()).is_empty());
+    }
+
+    fn shadowing_config(allowed: &[&str]) -> LintConfig {
+        LintConfig {
+            builtins: ["len", "print"].iter().map(ToString::to_string).collect(),
+            allowed_shadowing: allowed.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn local_shadowing_builtin_should_warn() {
+        let source = "def f(xs) =\n    var len = 0\n    len";
+        let warnings = lint(&parse(source), &shadowing_config(&[]));
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].position, Index(2, 9, 20));
+        assert_eq!(
+            warnings[0].message,
+            "len shadows builtin with the same name"
+        );
+    }
+
+    #[test]
+    fn allowed_builtin_shadowing_should_not_warn() {
+        let source = "def f(print) = print\nvar g = (len) => len";
+        assert_eq!(lint_messages(source, &shadowing_config(&[])).len(), 2);
+        assert!(lint_messages(source, &shadowing_config(&["len", "print"])).is_empty());
+    }
+
+    #[test]
+    fn annotation_generator_should_reject_duplicate_declaration() {
+        //NameRedefinitionChecker is skipped on purpose
+        let mut annotations = Annotations::new();
+        let error = AnnotationGenerator::generate_annotations(
+            &parse("def f =\n    var x = 1\n    var x = 2\n    x"),
+            &mut annotations,
+        )
+        .unwrap_err();
+        assert_eq!(error, "name x [3:9] is declared twice in the same scope");
+
+        let mut annotations = Annotations::new();
+        let error =
+            AnnotationGenerator::generate_annotations(&parse("def f(a, a) = a"), &mut annotations)
+                .unwrap_err();
+        assert_eq!(error, "name a [1:10] is declared twice in the same scope");
+    }
+
+    #[test]
+    fn variable_assigned_in_both_branches_should_be_readable() {
+        let source =
+            "def f(c) =\n    var a\n    if c\n        a = 1\n    else\n        a = 2\n    a";
+        assert!(check_optimize(parse(source)).is_ok());
+
+        //condition is always evaluated
+        let source = "var a\nvar b = if (a = 1; c) 1 else 2\nprint(a)";
+        assert!(check_optimize(parse(source)).is_ok());
+    }
+
+    #[test]
+    fn variable_assigned_in_one_branch_should_be_rejected() {
+        let source = "def f(c) =\n    var a\n    if c\n        a = 1\n    a";
+        let error = check_optimize(parse(source)).unwrap_err();
+        assert_eq!(
+            error,
+            "variable a [5:5] may be read before it is assigned, declared without value at [2:9]"
+        );
+
+        let source = "var a\nvar b = c or (a = 1; true)\nprint(a)";
+        assert!(check_optimize(parse(source)).is_err());
+    }
+
+    #[test]
+    fn variable_assigned_by_nested_function_should_not_be_checked() {
+        let source = "var a\ndef init() =\n    a = 1\ninit()\nprint(a)";
+        assert!(check_optimize(parse(source)).is_ok());
+    }
+
+    #[test]
+    fn overwritten_store_should_warn() {
+        let config = LintConfig {
+            dead_store: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            lint_messages("def f =\n    var a = 1\n    a = 2\n    a", &config),
+            vec!["value assigned to a is overwritten before being read [2:9]"]
+        );
+    }
+
+    #[test]
+    fn store_going_out_of_scope_should_warn() {
+        let config = LintConfig {
+            dead_store: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            lint_messages("def f(x) =\n    var a = x\n    a = a * 2\n    x", &config),
+            vec!["value assigned to a is never read [3:5]"]
+        );
+    }
+
+    #[test]
+    fn self_referential_update_should_not_warn() {
+        let config = LintConfig {
+            dead_store: true,
+            ..Default::default()
+        };
+        assert!(lint_messages("def f =\n    var a = 1\n    a = a + 1\n    a", &config).is_empty());
+    }
+
+    #[test]
+    fn conditional_or_captured_stores_should_not_warn() {
+        let config = LintConfig {
+            dead_store: true,
+            ..Default::default()
+        };
+        let conditional = "def f(c) =\n    var a = 1\n    if c\n        a = 2\n    a";
+        assert!(lint_messages(conditional, &config).is_empty());
+
+        let captured = "def f =\n    var a = 1\n    def g = a\n    a = 2\n    a = 3\n    g()";
+        assert!(lint_messages(captured, &config).is_empty());
 
-    Ok((tree, annotations))
+        //functions are visible before their declaration, so they may capture later variables
+        let captured_later = "def f =\n    def g = a\n    var a = 1\n    a = 2\n    g()";
+        assert!(lint_messages(captured_later, &config).is_empty());
+
+        //closures capture arguments by reference too
+        let captured_argument = "def f(x) =\n    var g = () => x\n    x = 5\n    g()";
+        assert!(lint_messages(captured_argument, &config).is_empty());
+
+        let global = "var a = 1\na = 2";
+        assert!(lint_messages(global, &config).is_empty());
+    }
+
+    #[test]
+    fn dead_store_lint_is_opt_in() {
+        let source = "def f =\n    var a = 1\n    a = 2\n    a";
+        assert!(lint_messages(source, &LintConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn pure_expression_statement_should_warn() {
+        let config = LintConfig {
+            discarded_value: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            lint_messages("def f(x) =\n    -x\n    x\n1 + 2\nf(1)", &config),
+            vec![
+                "expression has no effect and its value is discarded [2:5]",
+                "expression has no effect and its value is discarded [4:1]"
+            ]
+        );
+    }
+
+    #[test]
+    fn call_and_block_value_should_not_warn() {
+        let config = LintConfig {
+            discarded_value: true,
+            ..Default::default()
+        };
+        let source = "def f(x) =\n    print(x)\n    x * 2\nf(1)\nprint(f(2) + 1)\nf";
+        assert!(lint_messages(source, &config).is_empty());
+        assert!(lint_messages("1 + 2\nf(1)", &LintConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn operator_on_variables_should_not_warn() {
+        let config = LintConfig {
+            discarded_value: true,
+            ..Default::default()
+        };
+        //operator may be overloaded with method that has side effects
+        let source = "struct V:\n    x\nimpl V:\n    def __add__(self, o) = print(self.x)\n\
+                      var a = V(1)\na + a\na";
+        assert!(lint_messages(source, &config).is_empty());
+    }
+
+    #[test]
+    fn optimizations_should_keep_names_intact() {
+        let source = "const N = 4\n\
+                      var a = N * 2 + 1\n\
+                      def f(x, *rest) =\n    var y = x + N\n    (var z = y; z + len(rest))\n\
+                      struct P:\n    v\n\
+                      impl P:\n    def get(self) = self.v + N\n\
+                      var g = (k) => f(k) + a\n\
+                      var (b, [c]) = [1, [2]]\n\
+                      var r = try 1 / 0 catch (e) e";
+        let before = parse(source);
+        let (after, _) = check_optimize(before.clone()).unwrap();
+        assert_eq!(verify_optimization(&before, &after), Ok(()));
+    }
+
+    /// pass that mistakenly renames every reference to `x`
+    struct Renamer;
+
+    impl Rewriter<String> for Renamer {
+        fn visit_variable_expr(&mut self, mut variable_name: Token) -> Result<Expr, String> {
+            if variable_name.get_string() == Some("x") {
+                variable_name.kind = TokenKind::Name("y".to_string());
+            }
+            Ok(Expr::Name(variable_name))
+        }
+    }
+
+    #[test]
+    fn broken_pass_should_fail_verification() {
+        let before = parse("var x = 1\ndef f(v) = v + x");
+        let after = before
+            .clone()
+            .into_iter()
+            .map(|s| Renamer.visit_stmt(s))
+            .collect::<Result<Program, String>>()
+            .unwrap();
+        assert_eq!(
+            verify_optimization(&before, &after),
+            Err("optimization introduced free variable y [2:16]".to_string())
+        );
+
+        let dropped = parse("var x = 1");
+        assert_eq!(
+            verify_optimization(&before, &dropped),
+            Err("optimization changed top-level declarations: [x, f] became [x]".to_string())
+        );
+    }
 }
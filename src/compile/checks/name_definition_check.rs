@@ -1,17 +1,40 @@
 use crate::compile::checks::tree_visitor::Visitor;
-use crate::parsing::ast::{EnumVariant, Program, Stmt};
+use crate::parsing::ast::{takes_self, EnumVariant, Pattern, Program, Stmt};
 use crate::parsing::lexer::Token;
 use crate::Expr;
 use std::collections::HashMap;
 
-/// checks that names and arguments do not repeat in same scope
+/// checks that names and arguments do not repeat in same scope,
+/// that constants are never reassigned and that impl methods neither repeat nor clash with fields
 pub struct NameRedefinitionChecker {
     scope: Vec<HashMap<String, Token>>,
+    /// top-level constants, collected before visiting so that
+    /// functions declared earlier cannot assign to them
+    consts: HashMap<String, Token>,
+    /// fields of most recently declared struct or enum (all variants) with given name
+    type_fields: HashMap<String, Vec<Token>>,
+    /// methods implemented so far for type with given name, across all impl blocks
+    type_methods: HashMap<String, HashMap<String, Token>>,
 }
 
 impl NameRedefinitionChecker {
     pub fn check(ast: &Program) -> Result<(), String> {
-        let mut checker = NameRedefinitionChecker { scope: vec![] };
+        let consts = ast
+            .iter()
+            .filter_map(|s| match s {
+                Stmt::ConstDeclaration(name, _) => {
+                    Some((name.get_string().unwrap().to_string(), name.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let mut checker = NameRedefinitionChecker {
+            scope: vec![],
+            consts,
+            type_fields: HashMap::new(),
+            type_methods: HashMap::new(),
+        };
         checker.new_scope();
         ast.iter().try_for_each(|s| checker.visit_stmt(s))
     }
@@ -36,6 +59,64 @@ impl NameRedefinitionChecker {
             Ok(())
         }
     }
+
+    /// (re)declaring type forgets methods implemented for previous type with same name
+    fn declare_type(&mut self, name: &Token, fields: Vec<Token>) {
+        let name = name.get_string().unwrap();
+        self.type_fields.insert(name.to_string(), fields);
+        self.type_methods.remove(name);
+    }
+
+    /// associated functions live on type itself, so only methods may clash with fields
+    fn register_method(
+        &mut self,
+        type_name: &Token,
+        method: &Token,
+        is_method: bool,
+    ) -> Result<(), String> {
+        let type_name_str = type_name.get_string().unwrap();
+        let method_name = method.get_string().unwrap();
+
+        let clashing_field = self
+            .type_fields
+            .get(type_name_str)
+            .filter(|_| is_method)
+            .and_then(|fields| fields.iter().find(|f| f.get_string() == Some(method_name)));
+        if let Some(field) = clashing_field {
+            return Err(format!(
+                "method {} [{}] clashes with field of {}, declared at [{}]",
+                method_name, method.position, type_name_str, field.position
+            ));
+        }
+
+        let previous = self
+            .type_methods
+            .entry(type_name_str.to_string())
+            .or_default()
+            .insert(method_name.to_string(), method.clone());
+        if let Some(previous) = previous {
+            return Err(format!(
+                "method {} [{}] is already implemented for {} at [{}]",
+                method_name, method.position, type_name_str, previous.position
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// finds top-level constant referenced by name unless it is shadowed
+    fn lookup_const(&self, name: &str) -> Option<&Token> {
+        let shadowed = self
+            .scope
+            .iter()
+            .skip(1)
+            .any(|scope| scope.contains_key(name));
+        if shadowed {
+            None
+        } else {
+            self.consts.get(name)
+        }
+    }
 }
 
 impl Visitor<String> for NameRedefinitionChecker {
@@ -56,6 +137,80 @@ impl Visitor<String> for NameRedefinitionChecker {
         Ok(())
     }
 
+    fn visit_destructuring_stmt(&mut self, pattern: &Pattern, value: &Expr) -> Result<(), String> {
+        self.visit_expr(value)?;
+
+        for name in pattern.names() {
+            self.declare_name(name).map_err(|e| {
+                format!(
+                    "name {} [{}] is redefined in block, previous definition at [{}]",
+                    name.get_string().unwrap(),
+                    name.position,
+                    e.position
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_const_stmt(&mut self, name: &Token, value: &Expr) -> Result<(), String> {
+        if self.scope.len() > 1 {
+            return Err(format!(
+                "const {} [{}] can only be declared at top level",
+                name.get_string().unwrap(),
+                name.position
+            ));
+        }
+
+        self.visit_expr(value)?;
+
+        self.declare_name(name).map_err(|e| {
+            format!(
+                "name {} [{}] is redefined in block, previous definition at [{}]",
+                name.get_string().unwrap(),
+                name.position,
+                e.position
+            )
+        })
+    }
+
+    fn visit_assignment_stmt(&mut self, target: &Token, value: &Expr) -> Result<(), String> {
+        if let Some(definition) = self.lookup_const(target.get_string().unwrap()) {
+            return Err(format!(
+                "cannot assign to const {} [{}], declared at [{}]",
+                target.get_string().unwrap(),
+                target.position,
+                definition.position
+            ));
+        }
+
+        self.visit_expr(value)
+    }
+
+    fn visit_index_assignment(
+        &mut self,
+        target: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> Result<(), String> {
+        if let Expr::Name(name) = target {
+            if let Some(definition) = self.lookup_const(name.get_string().unwrap()) {
+                return Err(format!(
+                    "cannot assign into const {} [{}], declared at [{}]",
+                    name.get_string().unwrap(),
+                    bracket.position,
+                    definition.position
+                ));
+            }
+        }
+
+        self.visit_expr(target)?;
+        self.visit_expr(index)?;
+        self.visit_expr(value)
+    }
+
     fn visit_function_declaration_statement(
         &mut self,
         name: &Token,
@@ -154,6 +309,8 @@ impl Visitor<String> for NameRedefinitionChecker {
 
         self.pop_scope();
 
+        self.declare_type(name, fields.to_vec());
+
         Ok(())
     }
 
@@ -177,10 +334,21 @@ impl Visitor<String> for NameRedefinitionChecker {
             self.visit_struct_declaration_statement(&variant.name, &variant.fields)?;
         }
         self.pop_scope();
+
+        let fields = variants
+            .iter()
+            .flat_map(|variant| variant.fields.iter().cloned())
+            .collect();
+        self.declare_type(name, fields);
+
         Ok(())
     }
 
-    fn visit_impl_block(&mut self, _name: &Token, implementations: &[Stmt]) -> Result<(), String> {
+    fn visit_impl_block(
+        &mut self,
+        type_name: &Token,
+        implementations: &[Stmt],
+    ) -> Result<(), String> {
         self.new_scope();
 
         for f in implementations {
@@ -191,6 +359,7 @@ impl Visitor<String> for NameRedefinitionChecker {
                     vararg,
                     body,
                 } => {
+                    self.register_method(type_name, name, takes_self(args))?;
                     self.visit_method(name, args, vararg.as_ref(), body)?;
                 }
                 _ => unreachable!(),
@@ -218,22 +387,4 @@ impl Visitor<String> for NameRedefinitionChecker {
             )
         })
     }
-
-    fn visit_method(
-        &mut self,
-        name: &Token,
-        args: &[Token],
-        vararg: Option<&Token>,
-        body: &Expr,
-    ) -> Result<(), String> {
-        if args.is_empty() {
-            return Err(format!(
-                "method {} [{}] should have at least one argument",
-                name.get_string().unwrap(),
-                name.position
-            ));
-        }
-
-        self.visit_function_declaration_statement(name, args, vararg, body)
-    }
 }
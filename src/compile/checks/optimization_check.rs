@@ -0,0 +1,220 @@
+use super::tree_visitor::Visitor;
+use crate::parsing::ast::{EnumVariant, Expr, Pattern, Program, Stmt};
+use crate::parsing::lexer::{Index, Token};
+use std::collections::{BTreeMap, HashSet};
+
+/// compares program before and after optimization passes and fails if optimized program
+/// refers to a name that was not referenced before or declares different top-level names.
+/// Passes are expected to only remove references (e.g. by substituting constants)
+#[allow(dead_code)]
+pub fn verify_optimization(before: &Program, after: &Program) -> Result<(), String> {
+    let declared_before = top_level_names(before);
+    let declared_after = top_level_names(after);
+    if declared_before != declared_after {
+        return Err(format!(
+            "optimization changed top-level declarations: [{}] became [{}]",
+            declared_before.join(", "),
+            declared_after.join(", ")
+        ));
+    }
+
+    let free_before = FreeVariables::collect(before);
+    for (name, position) in FreeVariables::collect(after) {
+        if !free_before.contains_key(&name) {
+            return Err(format!(
+                "optimization introduced free variable {} [{}]",
+                name, position
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn top_level_names(program: &Program) -> Vec<&str> {
+    fn declared(stmt: &Stmt) -> Vec<&Token> {
+        match stmt {
+            Stmt::VarDeclaration(name, _) | Stmt::ConstDeclaration(name, _) => vec![name],
+            Stmt::DestructuringDeclaration(pattern, _) => pattern.names(),
+            Stmt::FunctionDeclaration { name, .. }
+            | Stmt::StructDeclaration { name, .. }
+            | Stmt::EnumDeclaration { name, .. } => vec![name],
+            Stmt::Import { name, rename, .. } => vec![rename.as_ref().unwrap_or(name)],
+            Stmt::Expression(Expr::SingleStatement(stmt)) => declared(stmt),
+            _ => vec![],
+        }
+    }
+
+    program
+        .iter()
+        .flat_map(declared)
+        .map(|name| name.get_string().unwrap())
+        .collect()
+}
+
+/// names that are read or assigned without being declared in enclosing scope,
+/// with position of first such use. Top-level names are visible everywhere
+#[derive(Default)]
+struct FreeVariables {
+    scopes: Vec<HashSet<String>>,
+    free: BTreeMap<String, Index>,
+}
+
+impl FreeVariables {
+    fn collect(program: &Program) -> BTreeMap<String, Index> {
+        let mut collector = FreeVariables {
+            scopes: vec![top_level_names(program)
+                .into_iter()
+                .map(str::to_string)
+                .collect()],
+            free: BTreeMap::new(),
+        };
+        for stmt in program {
+            let _ = collector.visit_stmt(stmt);
+        }
+        collector.free
+    }
+
+    fn declare(&mut self, name: &Token) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.get_string().unwrap().to_string());
+    }
+
+    fn reference(&mut self, name: &Token) {
+        let name_str = name.get_string().unwrap();
+        if !self.scopes.iter().any(|scope| scope.contains(name_str)) {
+            self.free
+                .entry(name_str.to_string())
+                .or_insert(name.position);
+        }
+    }
+
+    fn visit_function(
+        &mut self,
+        name: Option<&Token>,
+        args: &[Token],
+        vararg: Option<&Token>,
+        body: &Expr,
+    ) {
+        self.scopes.push(HashSet::new());
+        for declared in name.into_iter().chain(args).chain(vararg) {
+            self.declare(declared);
+        }
+        let _ = self.visit_expr(body);
+        self.scopes.pop();
+    }
+}
+
+impl Visitor<()> for FreeVariables {
+    fn visit_var_stmt(&mut self, name: &Token, rhs: Option<&Expr>) -> Result<(), ()> {
+        if let Some(rhs) = rhs {
+            self.visit_expr(rhs)?;
+        }
+        self.declare(name);
+        Ok(())
+    }
+
+    fn visit_destructuring_stmt(&mut self, pattern: &Pattern, value: &Expr) -> Result<(), ()> {
+        self.visit_expr(value)?;
+        for name in pattern.names() {
+            self.declare(name);
+        }
+        Ok(())
+    }
+
+    fn visit_const_stmt(&mut self, name: &Token, value: &Expr) -> Result<(), ()> {
+        self.visit_expr(value)?;
+        self.declare(name);
+        Ok(())
+    }
+
+    fn visit_assignment_stmt(&mut self, target: &Token, value: &Expr) -> Result<(), ()> {
+        self.visit_expr(value)?;
+        self.reference(target);
+        Ok(())
+    }
+
+    fn visit_function_declaration_statement(
+        &mut self,
+        name: &Token,
+        args: &[Token],
+        vararg: Option<&Token>,
+        body: &Expr,
+    ) -> Result<(), ()> {
+        self.declare(name);
+        self.visit_function(Some(name), args, vararg, body);
+        Ok(())
+    }
+
+    fn visit_struct_declaration_statement(
+        &mut self,
+        name: &Token,
+        _fields: &[Token],
+    ) -> Result<(), ()> {
+        self.declare(name);
+        Ok(())
+    }
+
+    fn visit_enum_declaration(
+        &mut self,
+        name: &Token,
+        _variants: &[EnumVariant],
+    ) -> Result<(), ()> {
+        self.declare(name);
+        Ok(())
+    }
+
+    fn visit_impl_block(&mut self, name: &Token, implementations: &[Stmt]) -> Result<(), ()> {
+        self.reference(name);
+        for implementation in implementations {
+            if let Stmt::FunctionDeclaration {
+                args, vararg, body, ..
+            } = implementation
+            {
+                self.visit_function(None, args, vararg.as_ref(), body);
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        _module: &[Token],
+        name: &Token,
+        rename: Option<&Token>,
+    ) -> Result<(), ()> {
+        self.declare(rename.unwrap_or(name));
+        Ok(())
+    }
+
+    fn visit_variable_expr(&mut self, variable_name: &Token) -> Result<(), ()> {
+        self.reference(variable_name);
+        Ok(())
+    }
+
+    fn visit_block(
+        &mut self,
+        _start_token: &Token,
+        _end_token: &Token,
+        containing_statements: &[Stmt],
+    ) -> Result<(), ()> {
+        self.scopes.push(HashSet::new());
+        for stmt in containing_statements {
+            self.visit_stmt(stmt)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn visit_anon_function_expr(
+        &mut self,
+        args: &[Token],
+        vararg: Option<&Token>,
+        _arrow: &Token,
+        body: &Expr,
+    ) -> Result<(), ()> {
+        self.visit_function(None, args, vararg, body);
+        Ok(())
+    }
+}
@@ -1,16 +1,23 @@
 #![allow(clippy::boxed_local)]
 
-use crate::parsing::ast::{EnumVariant, Stmt};
+use crate::parsing::ast::{EnumVariant, Pattern, Stmt};
 use crate::parsing::lexer::Token;
 use crate::Expr;
+use std::ops::Range;
 
 pub(super) trait Rewriter<E> {
     fn visit_stmt(&mut self, stmt: Stmt) -> Result<Stmt, E> {
         match stmt {
             Stmt::VarDeclaration(a, b) => self.visit_var_stmt(a, b),
+            Stmt::DestructuringDeclaration(pattern, value) => {
+                self.visit_destructuring_stmt(pattern, value)
+            }
+            Stmt::ConstDeclaration(name, value) => self.visit_const_stmt(name, value),
             Stmt::Assignment(target, value) => self.visit_assignment_stmt(target, value),
             Stmt::Expression(e) => self.visit_expr_stmt(e),
-            Stmt::Assert(keyword, value) => self.visit_assert_statement(keyword, value),
+            Stmt::Assert(keyword, value, span, message) => {
+                self.visit_assert_statement(keyword, value, span, message)
+            }
             Stmt::Pass(keyword) => self.visit_pass_stmt(keyword),
             Stmt::FunctionDeclaration {
                 name,
@@ -29,6 +36,10 @@ pub(super) trait Rewriter<E> {
                 self.visit_property_assignment(target, value)
             }
 
+            Stmt::IndexAssignment(target, bracket, index, value) => {
+                self.visit_index_assignment(target, bracket, index, value)
+            }
+
             Stmt::ImplBlock {
                 name,
                 implementations,
@@ -52,6 +63,17 @@ pub(super) trait Rewriter<E> {
         ))
     }
 
+    fn visit_destructuring_stmt(&mut self, pattern: Pattern, value: Expr) -> Result<Stmt, E> {
+        Ok(Stmt::DestructuringDeclaration(
+            pattern,
+            self.visit_expr(value)?,
+        ))
+    }
+
+    fn visit_const_stmt(&mut self, name: Token, value: Expr) -> Result<Stmt, E> {
+        Ok(Stmt::ConstDeclaration(name, self.visit_expr(value)?))
+    }
+
     fn visit_assignment_stmt(&mut self, target: Token, value: Expr) -> Result<Stmt, E> {
         Ok(Stmt::Assignment(target, self.visit_expr(value)?))
     }
@@ -60,8 +82,19 @@ pub(super) trait Rewriter<E> {
         Ok(Stmt::Expression(self.visit_expr(expr)?))
     }
 
-    fn visit_assert_statement(&mut self, keyword: Token, expr: Expr) -> Result<Stmt, E> {
-        Ok(Stmt::Assert(keyword, self.visit_expr(expr)?))
+    fn visit_assert_statement(
+        &mut self,
+        keyword: Token,
+        expr: Expr,
+        span: Range<usize>,
+        message: Option<Expr>,
+    ) -> Result<Stmt, E> {
+        let expr = self.visit_expr(expr)?;
+        let message = match message {
+            Some(message) => Some(self.visit_expr(message)?),
+            None => None,
+        };
+        Ok(Stmt::Assert(keyword, expr, span, message))
     }
 
     fn visit_pass_stmt(&mut self, keyword: Token) -> Result<Stmt, E> {
@@ -106,6 +139,21 @@ pub(super) trait Rewriter<E> {
         ))
     }
 
+    fn visit_index_assignment(
+        &mut self,
+        target: Expr,
+        bracket: Token,
+        index: Expr,
+        value: Expr,
+    ) -> Result<Stmt, E> {
+        Ok(Stmt::IndexAssignment(
+            self.visit_expr(target)?,
+            bracket,
+            self.visit_expr(index)?,
+            self.visit_expr(value)?,
+        ))
+    }
+
     fn visit_impl_block(&mut self, name: Token, functions: Vec<Stmt>) -> Result<Stmt, E> {
         let functions = functions
             .into_iter()
@@ -152,6 +200,17 @@ pub(super) trait Rewriter<E> {
             }
             Expr::PropertyAccess(target, prop) => self.visit_property_access(target, prop),
             Expr::PropertyTest(target, prop) => self.visit_property_check(target, prop),
+            Expr::MapLiteral(brace, entries) => self.visit_map_literal(brace, entries),
+            Expr::ListLiteral(bracket, items) => self.visit_list_literal(bracket, items),
+            Expr::TupleLiteral(paren, items) => self.visit_tuple_literal(paren, items),
+            Expr::Index(target, bracket, index) => self.visit_index_expr(target, bracket, index),
+            Expr::Slice(target, bracket, start, end) => {
+                self.visit_slice_expr(target, bracket, start, end)
+            }
+            Expr::Cast(value, op, target) => self.visit_cast_expr(value, op, target),
+            Expr::TryCatch(try_token, body, handler) => {
+                self.visit_try_expr(try_token, body, handler)
+            }
         }
     }
 
@@ -261,6 +320,81 @@ pub(super) trait Rewriter<E> {
         Ok(Expr::PropertyTest(target, property))
     }
 
+    fn visit_map_literal(&mut self, brace: Token, entries: Vec<(Expr, Expr)>) -> Result<Expr, E> {
+        let mut processed_entries = vec![];
+        for (key, value) in entries {
+            processed_entries.push((self.visit_expr(key)?, self.visit_expr(value)?));
+        }
+        Ok(Expr::MapLiteral(brace, processed_entries))
+    }
+
+    fn visit_list_literal(&mut self, bracket: Token, items: Vec<Expr>) -> Result<Expr, E> {
+        let items = items
+            .into_iter()
+            .map(|item| self.visit_expr(item))
+            .collect::<Result<Vec<_>, E>>()?;
+        Ok(Expr::ListLiteral(bracket, items))
+    }
+
+    fn visit_tuple_literal(&mut self, paren: Token, items: Vec<Expr>) -> Result<Expr, E> {
+        let items = items
+            .into_iter()
+            .map(|item| self.visit_expr(item))
+            .collect::<Result<Vec<_>, E>>()?;
+        Ok(Expr::TupleLiteral(paren, items))
+    }
+
+    fn visit_index_expr(
+        &mut self,
+        target: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    ) -> Result<Expr, E> {
+        let target = Box::new(self.visit_expr(*target)?);
+        let index = Box::new(self.visit_expr(*index)?);
+        Ok(Expr::Index(target, bracket, index))
+    }
+
+    fn visit_slice_expr(
+        &mut self,
+        target: Box<Expr>,
+        bracket: Token,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+    ) -> Result<Expr, E> {
+        let target = Box::new(self.visit_expr(*target)?);
+        let start = match start {
+            Some(start) => Some(Box::new(self.visit_expr(*start)?)),
+            None => None,
+        };
+        let end = match end {
+            Some(end) => Some(Box::new(self.visit_expr(*end)?)),
+            None => None,
+        };
+        Ok(Expr::Slice(target, bracket, start, end))
+    }
+
+    fn visit_cast_expr(
+        &mut self,
+        value: Box<Expr>,
+        op: Token,
+        target_type: Token,
+    ) -> Result<Expr, E> {
+        let value = Box::new(self.visit_expr(*value)?);
+        Ok(Expr::Cast(value, op, target_type))
+    }
+
+    fn visit_try_expr(
+        &mut self,
+        try_token: Token,
+        body: Box<Expr>,
+        handler: Box<Expr>,
+    ) -> Result<Expr, E> {
+        let body = Box::new(self.visit_expr(*body)?);
+        let handler = Box::new(self.visit_expr(*handler)?);
+        Ok(Expr::TryCatch(try_token, body, handler))
+    }
+
     fn visit_anon_function_expr(
         &mut self,
         args: Vec<Token>,
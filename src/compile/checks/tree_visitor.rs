@@ -1,4 +1,4 @@
-use crate::parsing::ast::{EnumVariant, Stmt};
+use crate::parsing::ast::{EnumVariant, Pattern, Stmt};
 use crate::parsing::lexer::Token;
 use crate::Expr;
 
@@ -7,9 +7,15 @@ pub(super) trait Visitor<E> {
     fn visit_stmt(&mut self, stmt: &Stmt) -> Result<(), E> {
         match stmt {
             Stmt::VarDeclaration(a, b) => self.visit_var_stmt(a, b.as_ref()),
+            Stmt::DestructuringDeclaration(pattern, value) => {
+                self.visit_destructuring_stmt(pattern, value)
+            }
+            Stmt::ConstDeclaration(name, value) => self.visit_const_stmt(name, value),
             Stmt::Assignment(target, value) => self.visit_assignment_stmt(target, value),
             Stmt::Expression(e) => self.visit_expr_stmt(e),
-            Stmt::Assert(keyword, value) => self.visit_assert_statement(keyword, value),
+            Stmt::Assert(keyword, value, _, message) => {
+                self.visit_assert_statement(keyword, value, message.as_ref())
+            }
             Stmt::Pass(keyword) => self.visit_pass_stmt(keyword),
             Stmt::FunctionDeclaration {
                 name,
@@ -27,6 +33,10 @@ pub(super) trait Visitor<E> {
                 self.visit_property_assignment(target, value)
             }
 
+            Stmt::IndexAssignment(target, bracket, index, value) => {
+                self.visit_index_assignment(target, bracket, index, value)
+            }
+
             Stmt::ImplBlock {
                 name,
                 implementations,
@@ -46,6 +56,14 @@ pub(super) trait Visitor<E> {
         Ok(())
     }
 
+    fn visit_destructuring_stmt(&mut self, _pattern: &Pattern, value: &Expr) -> Result<(), E> {
+        self.visit_expr(value)
+    }
+
+    fn visit_const_stmt(&mut self, _name: &Token, value: &Expr) -> Result<(), E> {
+        self.visit_expr(value)
+    }
+
     fn visit_assignment_stmt(&mut self, _target: &Token, value: &Expr) -> Result<(), E> {
         self.visit_expr(value)
     }
@@ -54,8 +72,17 @@ pub(super) trait Visitor<E> {
         self.visit_expr(expr)
     }
 
-    fn visit_assert_statement(&mut self, _keyword: &Token, expr: &Expr) -> Result<(), E> {
-        self.visit_expr(expr)
+    fn visit_assert_statement(
+        &mut self,
+        _keyword: &Token,
+        expr: &Expr,
+        message: Option<&Expr>,
+    ) -> Result<(), E> {
+        self.visit_expr(expr)?;
+        if let Some(message) = message {
+            self.visit_expr(message)?;
+        }
+        Ok(())
     }
 
     fn visit_pass_stmt(&mut self, _keyword: &Token) -> Result<(), E> {
@@ -99,6 +126,18 @@ pub(super) trait Visitor<E> {
         self.visit_expr(value)
     }
 
+    fn visit_index_assignment(
+        &mut self,
+        target: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> Result<(), E> {
+        self.visit_expr(target)?;
+        self.visit_expr(index)?;
+        self.visit_expr(value)
+    }
+
     fn visit_impl_block(&mut self, name: &Token, implementations: &[Stmt]) -> Result<(), E> {
         implementations
             .iter()
@@ -138,6 +177,17 @@ pub(super) trait Visitor<E> {
             }
             Expr::PropertyAccess(target, prop) => self.visit_property_access(target.as_ref(), prop),
             Expr::PropertyTest(target, prop) => self.visit_property_check(target.as_ref(), prop),
+            Expr::MapLiteral(brace, entries) => self.visit_map_literal(brace, entries),
+            Expr::ListLiteral(bracket, items) => self.visit_list_literal(bracket, items),
+            Expr::TupleLiteral(paren, items) => self.visit_tuple_literal(paren, items),
+            Expr::Index(target, bracket, index) => self.visit_index_expr(target, bracket, index),
+            Expr::Slice(target, bracket, start, end) => {
+                self.visit_slice_expr(target, bracket, start.as_deref(), end.as_deref())
+            }
+            Expr::Cast(value, op, target) => self.visit_cast_expr(value, op, target),
+            Expr::TryCatch(try_token, body, handler) => {
+                self.visit_try_expr(try_token, body, handler)
+            }
         }
     }
 
@@ -239,4 +289,57 @@ pub(super) trait Visitor<E> {
         self.visit_expr(target)?;
         Ok(())
     }
+
+    fn visit_map_literal(&mut self, brace: &Token, entries: &[(Expr, Expr)]) -> Result<(), E> {
+        for (key, value) in entries {
+            self.visit_expr(key)?;
+            self.visit_expr(value)?;
+        }
+        Ok(())
+    }
+
+    fn visit_list_literal(&mut self, bracket: &Token, items: &[Expr]) -> Result<(), E> {
+        for item in items {
+            self.visit_expr(item)?;
+        }
+        Ok(())
+    }
+
+    fn visit_tuple_literal(&mut self, paren: &Token, items: &[Expr]) -> Result<(), E> {
+        for item in items {
+            self.visit_expr(item)?;
+        }
+        Ok(())
+    }
+
+    fn visit_index_expr(&mut self, target: &Expr, bracket: &Token, index: &Expr) -> Result<(), E> {
+        self.visit_expr(target)?;
+        self.visit_expr(index)
+    }
+
+    fn visit_slice_expr(
+        &mut self,
+        target: &Expr,
+        bracket: &Token,
+        start: Option<&Expr>,
+        end: Option<&Expr>,
+    ) -> Result<(), E> {
+        self.visit_expr(target)?;
+        if let Some(start) = start {
+            self.visit_expr(start)?;
+        }
+        if let Some(end) = end {
+            self.visit_expr(end)?;
+        }
+        Ok(())
+    }
+
+    fn visit_cast_expr(&mut self, value: &Expr, op: &Token, target_type: &Token) -> Result<(), E> {
+        self.visit_expr(value)
+    }
+
+    fn visit_try_expr(&mut self, try_token: &Token, body: &Expr, handler: &Expr) -> Result<(), E> {
+        self.visit_expr(body)?;
+        self.visit_expr(handler)
+    }
 }
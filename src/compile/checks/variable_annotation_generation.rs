@@ -1,6 +1,6 @@
 use crate::compile::checks::tree_visitor::Visitor;
 use crate::compile::checks::{Annotations, VariableType};
-use crate::parsing::ast::{Program, Stmt};
+use crate::parsing::ast::{Pattern, Program, Stmt};
 use crate::parsing::lexer::Token;
 use crate::Expr;
 use std::collections::HashMap;
@@ -33,13 +33,24 @@ impl<'a> AnnotationGenerator<'a> {
         ast.iter().try_for_each(|s| annotator.visit_stmt(s))
     }
 
-    fn declare_name(&mut self, variable_name: &Token) {
-        self.scopes
+    /// declares name in current scope. Duplicates are normally rejected by
+    /// NameRedefinitionChecker, so error here means passes were run out of order
+    fn declare_name(&mut self, variable_name: &Token) -> Result<(), String> {
+        let previous = self
+            .scopes
             .last_mut()
             .unwrap()
             .2
             .insert(variable_name.get_string().unwrap().to_string(), false);
 
+        if previous.is_some() {
+            return Err(format!(
+                "name {} [{}] is declared twice in the same scope",
+                variable_name.get_string().unwrap(),
+                variable_name.position
+            ));
+        }
+
         self.annotations
             .get_or_create_block_scope(&self.scopes.last_mut().unwrap().1)
             .insert(
@@ -50,6 +61,8 @@ impl<'a> AnnotationGenerator<'a> {
                     VariableType::Normal
                 },
             );
+
+        Ok(())
     }
 
     fn define_name(&mut self, variable_name: &Token) {
@@ -141,6 +154,16 @@ impl<'a> Visitor<String> for AnnotationGenerator<'a> {
         Ok(())
     }
 
+    fn visit_destructuring_stmt(&mut self, pattern: &Pattern, value: &Expr) -> Result<(), String> {
+        self.visit_expr(value)?;
+
+        for name in pattern.names() {
+            self.define_name(name);
+        }
+
+        Ok(())
+    }
+
     fn visit_function_declaration_statement(
         &mut self,
         name: &Token,
@@ -151,7 +174,7 @@ impl<'a> Visitor<String> for AnnotationGenerator<'a> {
         self.new_scope(ScopeType::Function, name);
         self.annotations.get_or_create_closure_scope(name);
         for arg_name in args.iter().chain(vararg.into_iter()) {
-            self.declare_name(arg_name);
+            self.declare_name(arg_name)?;
             self.define_name(arg_name);
         }
         self.define_name(name);
@@ -179,23 +202,28 @@ impl<'a> Visitor<String> for AnnotationGenerator<'a> {
         for statement in containing_statements {
             match statement {
                 Stmt::VarDeclaration(name, _) => {
-                    self.declare_name(name);
+                    self.declare_name(name)?;
+                }
+                Stmt::DestructuringDeclaration(pattern, _) => {
+                    for name in pattern.names() {
+                        self.declare_name(name)?;
+                    }
                 }
                 Stmt::FunctionDeclaration { name, .. } => {
-                    self.declare_name(name);
+                    self.declare_name(name)?;
                 }
 
                 Stmt::StructDeclaration { name, .. } => {
-                    self.declare_name(name);
+                    self.declare_name(name)?;
                 }
 
                 Stmt::EnumDeclaration { name, .. } => {
-                    self.declare_name(name);
+                    self.declare_name(name)?;
                 }
 
                 Stmt::Import { name, rename, .. } => {
                     let import_name = rename.as_ref().unwrap_or(name);
-                    self.declare_name(import_name);
+                    self.declare_name(import_name)?;
                 }
 
                 _ => {}
@@ -220,7 +248,7 @@ impl<'a> Visitor<String> for AnnotationGenerator<'a> {
         self.new_scope(ScopeType::Function, arrow);
         self.annotations.get_or_create_closure_scope(arrow);
         for arg_name in args.iter().chain(vararg.into_iter()) {
-            self.declare_name(arg_name);
+            self.declare_name(arg_name)?;
             self.define_name(arg_name);
         }
 
@@ -5,8 +5,8 @@ use crate::data::objects::{EnumDescriptor, StackObject, StructDescriptor, Value}
 use crate::execution::arity::Arity;
 use crate::execution::chunk::{Chunk, Opcode};
 use crate::execution::module::Module;
-use crate::parsing::ast::{Expr, Program, Stmt};
-use crate::parsing::lexer::{Index, Token, TokenKind};
+use crate::parsing::ast::{takes_self, Expr, Program, Stmt};
+use crate::parsing::lexer::{strip_comment, Index, Token, TokenKind};
 use regex::Regex;
 use std::collections::HashMap;
 
@@ -17,21 +17,26 @@ enum ValueRequirement {
 }
 
 lazy_static! {
+    /// name of top-level code, which starts at beginning of source
     pub static ref SCRIPT_TOKEN: Token = Token {
         kind: TokenKind::Name("`script`".to_string()),
-        position: Index(0, 0),
+        position: Index(1, 1, 0),
     };
     static ref FIELD_INDEX_REGEX: Regex = Regex::new(r"^_\d+$").unwrap();
 }
 
-pub struct Compiler<'gc, 'annotations, 'chunk> {
+pub struct Compiler<'gc, 'annotations, 'chunk, 'source> {
     names: Vec<HashMap<String, (VariableType, bool, usize)>>,
     value_requirements: Vec<ValueRequirement>,
     total_closed_variables: usize,
     stack_height: usize,
+    /// values of top-level constants, loaded directly at usage
+    const_values: HashMap<String, Value>,
     function_context: FunctionCompilationContext,
     current_chunk: &'chunk mut Chunk,
     annotations: &'annotations Annotations,
+    /// source program was parsed from, default messages of asserts are sliced from it
+    source: &'source str,
     gc: &'gc mut GC,
 }
 
@@ -40,31 +45,35 @@ struct FunctionCompilationContext {
     name: Token,
 }
 
-impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
+impl<'gc, 'annotations, 'chunk, 'source> Compiler<'gc, 'annotations, 'chunk, 'source> {
     fn new(
         annotations: &'annotations Annotations,
+        source: &'source str,
         gc: &'gc mut GC,
         function_name: Token,
         function_arity: Arity,
         chunk: &'chunk mut Chunk,
-    ) -> Compiler<'gc, 'annotations, 'chunk> {
+    ) -> Compiler<'gc, 'annotations, 'chunk, 'source> {
         Compiler {
             names: vec![],
             value_requirements: vec![],
             total_closed_variables: 0,
             stack_height: 0,
+            const_values: HashMap::new(),
             function_context: FunctionCompilationContext {
                 arity: function_arity,
                 name: function_name,
             },
             current_chunk: chunk,
             annotations,
+            source,
             gc,
         }
     }
 
     pub fn compile_module(
         program: &Program,
+        source: &'source str,
         annotations: Annotations,
         module: Module,
         gc: &'gc mut GC,
@@ -73,6 +82,7 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
 
         let mut compiler = Compiler::new(
             &annotations,
+            source,
             gc,
             SCRIPT_TOKEN.clone(),
             Arity::Exact(0),
@@ -81,6 +91,21 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
 
         compiler.new_scope();
 
+        for stmt in program {
+            if let Stmt::ConstDeclaration(name, value) = stmt {
+                let value = compiler.make_literal_value(value).ok_or_else(|| {
+                    format!(
+                        "initializer of const {} [{}] is not a constant expression",
+                        name.get_string().unwrap(),
+                        name.position
+                    )
+                })?;
+                compiler
+                    .const_values
+                    .insert(name.get_string().unwrap().to_string(), value);
+            }
+        }
+
         for stmt in program {
             match stmt {
                 Stmt::VarDeclaration(name, _)
@@ -90,6 +115,12 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
                     compiler.declare_local(name.get_string().unwrap(), VariableType::Global);
                 }
 
+                Stmt::DestructuringDeclaration(pattern, _) => {
+                    for name in pattern.names() {
+                        compiler.declare_local(name.get_string().unwrap(), VariableType::Global);
+                    }
+                }
+
                 Stmt::Import { name, rename, .. } => {
                     let name = rename.as_ref().unwrap_or(name);
                     compiler.declare_local(name.get_string().unwrap(), VariableType::Global);
@@ -306,8 +337,15 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
 
         let mut chunk = Chunk::new(name.clone(), self.current_chunk.module.clone(), arity);
 
-        let mut inner_compiler =
-            Compiler::new(self.annotations, self.gc, name.clone(), arity, &mut chunk);
+        let mut inner_compiler = Compiler::new(
+            self.annotations,
+            self.source,
+            self.gc,
+            name.clone(),
+            arity,
+            &mut chunk,
+        );
+        inner_compiler.const_values = self.const_values.clone();
 
         //compile body
 
@@ -503,15 +541,31 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
             }
 
             None => {
-                //global
-                let idx = self.get_or_create_name(name.get_string().unwrap());
+                if let Some(value) = self.const_values.get(name.get_string().unwrap()).cloned() {
+                    let idx = self.get_or_create_constant(value);
+                    result.push(Opcode::LoadConst(idx as u16), line);
+                } else {
+                    //global
+                    let idx = self.get_or_create_name(name.get_string().unwrap());
 
-                result.push(Opcode::LoadGlobal(idx as u16), line);
+                    result.push(Opcode::LoadGlobal(idx as u16), line);
+                }
             }
         }
         Ok(result)
     }
 
+    /// converts folded literal into runtime value
+    fn make_literal_value(&mut self, expr: &Expr) -> Option<Value> {
+        match expr {
+            Expr::Number(n) => n.get_number().map(Value::Int),
+            Expr::FloatNumber(n) => n.get_float().map(Value::from),
+            Expr::Bool(b) => Some((b.kind == TokenKind::True).into()),
+            Expr::ConstString(s) => Some(self.gc.new_interned_string(s.get_string().unwrap())),
+            _ => None,
+        }
+    }
+
     fn try_parse_special_field_access(property: &Token) -> Result<Option<u16>, String> {
         if FIELD_INDEX_REGEX.is_match(property.get_string().unwrap()) {
             let idx = (property.get_string().unwrap()[1..])
@@ -538,6 +592,7 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
                 .map(|f| f.get_string().unwrap().to_string())
                 .collect(),
             methods: HashMap::new(),
+            associated: HashMap::new(),
             enum_ref: None,
         };
 
@@ -570,11 +625,71 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
                 }
             }
 
+            Stmt::DestructuringDeclaration(pattern, value) => {
+                let pattern_token = pattern.token();
+                let line = pattern_token.position.0;
+
+                //destructured value is kept in hidden slot while names are bound
+                let hidden_name = format!("`destructured {}`", pattern_token.position);
+                let hidden_token = Token {
+                    kind: TokenKind::Name(hidden_name.clone()),
+                    position: pattern_token.position,
+                };
+
+                let value_code = |slf: &mut Compiler| {
+                    slf.require_value();
+                    let value_code = slf.visit_expr(value)?;
+                    slf.pop_requirement();
+                    Ok(value_code)
+                };
+
+                result.append(self.create_named_entity(&hidden_token, &value_code)?);
+
+                let (_, hidden_idx) = self.lookup_local(&hidden_name).unwrap();
+
+                for (name, path) in pattern.bindings() {
+                    let element_code = |slf: &mut Compiler| {
+                        let mut element_code = AnnotatedCodeBlob::new();
+                        element_code.push(Opcode::LoadLocal(hidden_idx as u16), line);
+                        for &(length, index) in &path {
+                            element_code.push(Opcode::CheckLength(length as u16), line);
+                            element_code.push(Opcode::LoadElement(index as u16), line);
+                        }
+                        slf.inc_stack_height();
+                        Ok(element_code)
+                    };
+
+                    result.append(self.create_named_entity(name, &element_code)?);
+                }
+
+                if self.needs_value() {
+                    result.push(Opcode::LoadNothing, line);
+                }
+            }
+
+            Stmt::ConstDeclaration(name, _) => {
+                //usages load value directly, global is only stored for imports
+                let value = self
+                    .const_values
+                    .get(name.get_string().unwrap())
+                    .cloned()
+                    .unwrap();
+                let const_idx = self.get_or_create_constant(value);
+                let name_idx = self.get_or_create_name(name.get_string().unwrap());
+                result.push(Opcode::LoadConst(const_idx as u16), name.position.0);
+                result.push(Opcode::StoreGLobal(name_idx as u16), name.position.0);
+
+                if self.needs_value() {
+                    result.push(Opcode::LoadNothing, name.position.0);
+                }
+            }
+
             Stmt::EnumDeclaration { name, variants } => {
                 let descriptor = EnumDescriptor {
                     name: name.get_string().unwrap().to_string(),
                     variants: Default::default(),
                     methods: Default::default(),
+                    associated: Default::default(),
                 };
 
                 let descriptor = self.gc.store(descriptor);
@@ -646,10 +761,14 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
                             result.append(self.close_function(name)?);
                             //function on top of pointer
 
+                            let name_idx =
+                                self.get_or_create_name(name.get_string().unwrap()) as u16;
                             result.push(
-                                Opcode::StoreField(
-                                    self.get_or_create_name(name.get_string().unwrap()) as u16,
-                                ),
+                                if takes_self(args) {
+                                    Opcode::StoreField(name_idx)
+                                } else {
+                                    Opcode::StoreAssociated(name_idx)
+                                },
                                 name.position.0,
                             );
 
@@ -748,6 +867,7 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
 
                     result.append(target); //load pointer
                     result.append(value); // value on top of pointer
+                    self.sub_stack_height(2); //both are consumed by store
 
                     //special field index access
                     result += (
@@ -770,17 +890,71 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
                 other => return Err(format!("unsupported assignment target {:?}", other)),
             },
 
+            Stmt::IndexAssignment(target, bracket, index, value) => {
+                for part in [target, index, value] {
+                    self.require_value();
+                    result.append(self.visit_expr(part)?);
+                    self.pop_requirement();
+                }
+
+                result.push(Opcode::StoreIndex, bracket.position.0);
+                self.sub_stack_height(3); //target, index and value are consumed by store
+
+                if self.needs_value() {
+                    result.push(Opcode::LoadNothing, bracket.position.0);
+                }
+            }
+
             Stmt::Expression(e) => {
                 let body = self.visit_expr(e)?;
                 result.append(body);
             }
 
-            Stmt::Assert(token, expr) => {
+            Stmt::Assert(token, expr, span, message) => {
                 self.require_value();
                 let body = self.visit_expr(expr)?;
                 self.pop_requirement();
                 result.append(body);
+
+                //assertion without message reports its own source
+                let default_message;
+                let message = match message {
+                    Some(message) => message,
+                    None => {
+                        default_message = Expr::ConstString(Token {
+                            position: token.position,
+                            kind: TokenKind::ConstString(format!(
+                                "assertion failed: `{}`",
+                                strip_comment(&self.source[span.clone()]).trim_end()
+                            )),
+                        });
+                        &default_message
+                    }
+                };
+
+                /*
+                    eval(cond)
+                    jump_if_true_or_pop L
+                    eval(message)   //only evaluated when assertion fails
+                    fail_assertion
+                L:  assert
+                */
+                self.dec_stack_height(); //condition is popped if message is evaluated
+                self.require_value();
+                let message = self.visit_expr(message)?;
+                self.pop_requirement();
+                self.dec_stack_height(); //message is consumed by fail_assertion
+                self.inc_stack_height(); //condition is still on stack at L
+
+                result.push(
+                    Opcode::JumpIfTrueOrPop((message.code.len() + 2) as u16),
+                    token.position.0,
+                );
+                result.append(message);
+                result.push(Opcode::FailAssertion, token.position.0);
+
                 result.push(Opcode::Assert, token.position.0);
+                self.dec_stack_height(); //checked value is consumed
                 if self.needs_value() {
                     result.push(Opcode::LoadNothing, token.position.0);
                 }
@@ -922,6 +1096,8 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
                 result.push(
                     match &op.kind {
                         TokenKind::Not => Opcode::LogicalNot,
+                        TokenKind::Minus => Opcode::Negate,
+                        TokenKind::Tilde => Opcode::BitwiseNot,
                         other => {
                             panic!("unimplemented unary operator {} [{}]", other, op.position)
                         }
@@ -1109,9 +1285,9 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
                     if !self.function_context.arity.is_vararg()
                         && args.len() > self.function_context.arity.into()
                     {
-                        return Err(format!("compile error: arity mismatch when performing tail call: expected {} but got {} args", 
-                                           self.function_context.arity,
-                                           args.len()
+                        return Err(format!(
+                            "compile error: arity mismatch when performing tail call: {}",
+                            self.function_context.arity.mismatch_message(args.len())
                         ));
                     }
 
@@ -1239,6 +1415,7 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
                 self.require_value();
                 let target = self.visit_expr(target.as_ref())?;
                 self.pop_requirement();
+                self.dec_stack_height(); // target is replaced by result in outer code
 
                 result.append(target);
 
@@ -1262,6 +1439,7 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
                 self.require_value();
                 let target = self.visit_expr(target.as_ref())?;
                 self.pop_requirement();
+                self.dec_stack_height(); // target is replaced by result in outer code
 
                 result.append(target);
 
@@ -1273,6 +1451,141 @@ impl<'gc, 'annotations, 'chunk> Compiler<'gc, 'annotations, 'chunk> {
                     result.push(Opcode::Pop(1), prop.position.0);
                 }
             }
+
+            Expr::Cast(value, op, target_type) => {
+                let cast = match target_type.get_string().unwrap() {
+                    "Int" => Opcode::CastToInt,
+                    "Float" => Opcode::CastToFloat,
+                    "String" => Opcode::CastToString,
+                    other => {
+                        return Err(format!(
+                            "unknown cast target {} [{}], expected Int, Float or String",
+                            other, target_type.position
+                        ))
+                    }
+                };
+
+                self.require_value();
+                result.append(self.visit_expr(value)?);
+                self.pop_requirement();
+                self.dec_stack_height(); // value is replaced by result in outer code
+
+                result.push(cast, op.position.0);
+
+                if !self.needs_value() {
+                    result.push(Opcode::Pop(1), op.position.0);
+                }
+            }
+
+            Expr::TryCatch(try_token, body, handler) => {
+                //handler function stays under body value until body finishes
+                self.require_value();
+                result.append(self.visit_expr(handler)?);
+                self.pop_requirement();
+
+                self.require_value();
+                let body = self.visit_expr(body)?;
+                self.pop_requirement();
+                self.sub_stack_height(2); // both are replaced by result in outer code
+
+                //jump to call of handler, placed after body, PopHandler and jump
+                result.push(
+                    Opcode::PushHandler((body.code.len() + 3) as u16),
+                    try_token.position.0,
+                );
+                result.append(body);
+                result.push(Opcode::PopHandler, try_token.position.0);
+                result.push(Opcode::JumpRelative(2), try_token.position.0);
+                //on error stack is cut down to handler and error value is pushed
+                result.push(Opcode::Call(1), try_token.position.0);
+
+                if !self.needs_value() {
+                    result.push(Opcode::Pop(1), try_token.position.0);
+                }
+            }
+
+            Expr::MapLiteral(brace, entries) => {
+                for (key, value) in entries {
+                    self.require_value();
+                    result.append(self.visit_expr(key)?);
+                    self.pop_requirement();
+
+                    self.require_value();
+                    result.append(self.visit_expr(value)?);
+                    self.pop_requirement();
+                }
+
+                result.push(Opcode::MakeMap(entries.len() as u16), brace.position.0);
+                self.sub_stack_height(entries.len() * 2); //keys and values are removed
+                                                          //map is added in outer code
+
+                if !self.needs_value() {
+                    result.push(Opcode::Pop(1), brace.position.0);
+                }
+            }
+
+            Expr::ListLiteral(bracket, items) | Expr::TupleLiteral(bracket, items) => {
+                for item in items {
+                    self.require_value();
+                    result.append(self.visit_expr(item)?);
+                    self.pop_requirement();
+                }
+
+                //elements stay on stack until list is built inside single instruction,
+                //so GC (which only runs between instructions) always sees them as rooted
+                result.push(Opcode::MakeList(items.len() as u16), bracket.position.0);
+                self.sub_stack_height(items.len()); //list is added in outer code
+
+                if !self.needs_value() {
+                    result.push(Opcode::Pop(1), bracket.position.0);
+                }
+            }
+
+            Expr::Index(target, bracket, index) => {
+                self.require_value();
+                result.append(self.visit_expr(target)?);
+                self.pop_requirement();
+
+                self.require_value();
+                result.append(self.visit_expr(index)?);
+                self.pop_requirement();
+
+                result.push(Opcode::LoadIndex, bracket.position.0);
+                self.dec_stack_height(); // target is removed
+                self.dec_stack_height(); // index is removed, result is added in outer code
+
+                if !self.needs_value() {
+                    result.push(Opcode::Pop(1), bracket.position.0);
+                }
+            }
+
+            Expr::Slice(target, bracket, start, end) => {
+                self.require_value();
+                result.append(self.visit_expr(target)?);
+                self.pop_requirement();
+
+                //missing bound is passed as Nothing
+                for bound in [start, end] {
+                    match bound {
+                        Some(bound) => {
+                            self.require_value();
+                            result.append(self.visit_expr(bound)?);
+                            self.pop_requirement();
+                        }
+                        None => {
+                            result.push(Opcode::LoadNothing, bracket.position.0);
+                            self.inc_stack_height();
+                        }
+                    }
+                }
+
+                result.push(Opcode::LoadSlice, bracket.position.0);
+                self.sub_stack_height(3); //result is added in outer code
+
+                if !self.needs_value() {
+                    result.push(Opcode::Pop(1), bracket.position.0);
+                }
+            }
         }
 
         Ok(result)
@@ -1375,7 +1688,11 @@ mod complex_operators_stack_tests {
     use crate::{
         compile::compiler::SCRIPT_TOKEN,
         data::gc::GC,
-        execution::{arity::Arity, chunk::Chunk, module::Module},
+        execution::{
+            arity::Arity,
+            chunk::{Chunk, Opcode},
+            module::Module,
+        },
         parsing::{
             ast::Expr,
             lexer::{Index, Token, TokenKind},
@@ -1383,7 +1700,7 @@ mod complex_operators_stack_tests {
     };
 
     static ZERO: Token = Token {
-        position: Index(0, 0),
+        position: Index(0, 0, 0),
         kind: TokenKind::Number(0),
     };
 
@@ -1398,6 +1715,7 @@ mod complex_operators_stack_tests {
         let annotations = Default::default();
         let mut compiler = Compiler::new(
             &annotations,
+            "",
             &mut gc,
             SCRIPT_TOKEN.clone(),
             Arity::Exact(0),
@@ -1425,7 +1743,7 @@ mod complex_operators_stack_tests {
         let ast = Expr::Binary(
             Token {
                 kind: TokenKind::Or,
-                position: Index(0, 0),
+                position: Index(0, 0, 0),
             },
             Box::new(Expr::Number(ZERO.clone())),
             Box::new(Expr::Number(ZERO.clone())),
@@ -1439,7 +1757,7 @@ mod complex_operators_stack_tests {
         let ast = Expr::Binary(
             Token {
                 kind: TokenKind::And,
-                position: Index(0, 0),
+                position: Index(0, 0, 0),
             },
             Box::new(Expr::Number(ZERO.clone())),
             Box::new(Expr::Number(ZERO.clone())),
@@ -1453,7 +1771,7 @@ mod complex_operators_stack_tests {
         let ast = Expr::Binary(
             Token {
                 kind: TokenKind::Plus,
-                position: Index(0, 0),
+                position: Index(0, 0, 0),
             },
             Box::new(Expr::Number(ZERO.clone())),
             Box::new(Expr::Number(ZERO.clone())),
@@ -1461,4 +1779,36 @@ mod complex_operators_stack_tests {
 
         compile_ast_with_value(gc, ast);
     }
+
+    #[rstest]
+    fn list_literal_should_be_built_by_single_instruction(mut gc: GC) {
+        let bracket = Token {
+            kind: TokenKind::LBracket,
+            position: Index(0, 0, 0),
+        };
+        let ast = Expr::ListLiteral(bracket, vec![Expr::Number(ZERO.clone()); 100]);
+
+        let module = Module::from_dot_notation("`TEST`");
+        let mut chunk = Chunk::new(SCRIPT_TOKEN.clone(), module, Arity::Exact(0));
+        let annotations = Default::default();
+        let mut compiler = Compiler::new(
+            &annotations,
+            "",
+            &mut gc,
+            SCRIPT_TOKEN.clone(),
+            Arity::Exact(0),
+            &mut chunk,
+        );
+
+        compiler.require_value();
+        let code = compiler.visit_expr(&ast).unwrap().code;
+        compiler.pop_requirement();
+        assert_eq!(compiler.stack_height, 1);
+
+        assert_eq!(code.len(), 101); //one load per element and a single build
+        assert_eq!(code.last(), Some(&Opcode::MakeList(100)));
+        assert!(!code[..100]
+            .iter()
+            .any(|op| matches!(op, Opcode::MakeList(..))));
+    }
 }
@@ -12,11 +12,41 @@ use crate::data::objects::{Closure, Partial, Value, ValueBox, SHORT_STRING_BUF_S
 use crate::execution::arity::Arity;
 use crate::execution::chunk::Chunk;
 use crate::execution::vm::CallStackValue;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
 use std::pin::Pin;
 use std::ptr::NonNull;
 
 const GC_THR_DEFAULT: usize = 1000;
 
+/// number of live heap objects of every type, produced by [`GC::report`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeapReport {
+    pub objects: BTreeMap<&'static str, usize>,
+}
+
+#[allow(dead_code)]
+impl HeapReport {
+    pub fn total(&self) -> usize {
+        self.objects.values().sum()
+    }
+
+    pub fn count(&self, type_name: &str) -> usize {
+        self.objects.get(type_name).copied().unwrap_or(0)
+    }
+}
+
+impl Display for HeapReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let entries = self
+            .objects
+            .iter()
+            .map(|(type_name, count)| format!("{}: {}", type_name, count))
+            .collect::<Vec<_>>();
+        write!(f, "{}", entries.join(", "))
+    }
+}
+
 pub struct GC {
     objects: IntMap<usize, Pin<Box<OwnedObject>>>,
     allocations: usize,
@@ -150,7 +180,7 @@ impl OwnedObject {
                 }
             }
             OwnedObjectItem::StructDescriptor(d) => {
-                for method in d.methods.values() {
+                for method in d.methods.values().chain(d.associated.values()) {
                     method.mark(value);
                 }
 
@@ -164,7 +194,7 @@ impl OwnedObject {
                     variant.mark(value);
                 }
 
-                for method in d.methods.values() {
+                for method in d.methods.values().chain(d.associated.values()) {
                     method.mark(value);
                 }
             }
@@ -239,7 +269,7 @@ impl OwnedObject {
             }
 
             OwnedObjectItem::StructDescriptor(d) => {
-                let f = !d.methods.is_empty() || d.enum_ref.is_some();
+                let f = !d.methods.is_empty() || !d.associated.is_empty() || d.enum_ref.is_some();
                 #[cfg(feature = "verbose-gc")]
                 println!(
                     "taking enum_ref in {ptr:x} which is {}",
@@ -249,14 +279,16 @@ impl OwnedObject {
                 #[cfg(feature = "verbose-gc")]
                 println!("clearing methods in struct {ptr:#x}");
                 d.methods.clear();
+                d.associated.clear();
                 f
             }
 
             OwnedObjectItem::EnumDescriptor(d) => {
-                let f = !d.methods.is_empty() || !d.variants.is_empty();
+                let f = !d.methods.is_empty() || !d.associated.is_empty() || !d.variants.is_empty();
                 #[cfg(feature = "verbose-gc")]
                 println!("clearing methods in enum {ptr:#x}");
                 d.methods.clear();
+                d.associated.clear();
                 #[cfg(feature = "verbose-gc")]
                 println!("clearing variants in enum {ptr:#x}");
                 d.variants.clear();
@@ -546,7 +578,8 @@ impl GC {
         self.allocations >= self.allocations_threshold
     }
 
-    /// (maybe) collects garbage from internal list of objects (created with allocate_new or store)
+    /// collects garbage from internal list of objects (created with allocate_new or store).
+    /// Callers are expected to check [`GC::needs_collection`] first unless collection is forced
     ///
     /// # Arguments
     ///
@@ -560,10 +593,6 @@ impl GC {
     where
         I: Iterator<Item = &'a StackObject>,
     {
-        if !self.needs_collection() {
-            return;
-        }
-
         #[cfg(feature = "debug-gc")]
         println!("begin slow_pass");
 
@@ -607,6 +636,15 @@ impl GC {
         println!("end slow_pass");
     }
 
+    /// counts objects currently owned by this GC by their type
+    pub fn report(&self) -> HeapReport {
+        let mut report = HeapReport::default();
+        for object in self.objects.values() {
+            *report.objects.entry(object.type_string()).or_default() += 1;
+        }
+        report
+    }
+
     /// drop object identified by address `addr` (probably produced by GC::get_addressable_index)
     ///
     /// This function is unsafe because in non-debug environment existence of pointers to named
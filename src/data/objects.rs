@@ -84,14 +84,9 @@ impl Partial {
     pub fn new(target: Value, target_arity: Arity, args: Vec<Value>) -> Self {
         let blanks = Self::count_blanks(&args);
 
-        let my_arity = match target_arity {
-            Arity::Exact(_) => Arity::Exact(blanks),
-            Arity::AtLeast(_) => Arity::AtLeast(blanks),
-        };
-
         Partial {
             target,
-            arity: my_arity,
+            arity: target_arity.residual(blanks),
             args,
         }
     }
@@ -108,14 +103,9 @@ impl Partial {
         args[0] = value;
         let blanks = Self::count_blanks(&args);
 
-        let arity = match self.arity {
-            Arity::Exact(_) => Arity::Exact(blanks),
-            Arity::AtLeast(_) => Arity::AtLeast(blanks),
-        };
-
         Partial {
             target: self.target.clone(),
-            arity,
+            arity: self.arity.residual(blanks),
             args,
         }
     }
@@ -145,14 +135,9 @@ impl Partial {
 
         let blanks = Self::count_blanks(&args);
 
-        let arity = match self.arity {
-            Arity::Exact(_) => Arity::Exact(blanks),
-            Arity::AtLeast(_) => Arity::AtLeast(blanks),
-        };
-
         Partial {
             target: self.target.clone(),
-            arity,
+            arity: self.arity.residual(blanks),
             args,
         }
     }
@@ -167,6 +152,8 @@ pub struct StructDescriptor {
     pub name: String,
     pub fields: Vec<String>,
     pub(crate) methods: HashMap<String, Value>,
+    /// functions of impl block without `self`, only reachable through descriptor
+    pub(crate) associated: HashMap<String, Value>,
     pub enum_ref: Option<Value>,
 }
 
@@ -195,6 +182,10 @@ impl StructDescriptor {
         self.methods.insert(method_name.to_string(), method);
     }
 
+    pub fn add_associated(&mut self, function_name: &str, function: Value) {
+        self.associated.insert(function_name.to_string(), function);
+    }
+
     pub fn get_method(&self, method_name: &str) -> Option<Value> {
         self.methods.get(method_name).cloned().or_else(|| {
             self.enum_ref
@@ -230,6 +221,7 @@ pub struct EnumDescriptor {
     pub name: String,
     pub variants: IndexMap<String, Value>,
     pub(crate) methods: HashMap<String, Value>,
+    pub(crate) associated: HashMap<String, Value>,
 }
 
 impl EnumDescriptor {
@@ -237,6 +229,10 @@ impl EnumDescriptor {
         self.methods.insert(method_name.to_string(), method);
     }
 
+    pub fn add_associated(&mut self, function_name: &str, function: Value) {
+        self.associated.insert(function_name.to_string(), function);
+    }
+
     pub fn get_method(&self, method_name: &str) -> Option<Value> {
         self.methods.get(method_name).cloned()
     }
@@ -246,6 +242,7 @@ impl EnumDescriptor {
             .get(field_name)
             .cloned()
             .or_else(|| self.methods.get(field_name).cloned())
+            .or_else(|| self.associated.get(field_name).cloned())
     }
 
     ///adds variant to internal map, registers itself inside variant struct
@@ -259,12 +256,30 @@ impl EnumDescriptor {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct StructInstance {
     pub descriptor: Value,
     pub fields: IndexMap<String, Value>,
 }
 
+/// instances are equal if they are created from the same descriptor and their fields are equal.
+/// Descriptors are compared by identity, so structs declared separately never compare equal
+/// even if their names and fields match
+impl PartialEq for StructInstance {
+    fn eq(&self, other: &Self) -> bool {
+        let same_descriptor = match (
+            self.descriptor.as_heap_object(),
+            other.descriptor.as_heap_object(),
+        ) {
+            (Some(first), Some(second)) => std::ptr::eq(first, second),
+            _ => false,
+        };
+        same_descriptor && self.fields == other.fields
+    }
+}
+
+impl Eq for StructInstance {}
+
 impl StructInstance {
     pub fn get_bound_method(
         &self,
@@ -582,6 +597,7 @@ impl StackObject {
         !matches!(self, StackObject::Nothing | StackObject::Bool(false))
     }
 
+    /// number of arguments value accepts when called, `None` if it is not callable
     pub fn get_arity(&self, context: &mut VM) -> Option<Arity> {
         match self {
             StackObject::Int(_) => None,
@@ -612,7 +628,7 @@ impl StackObject {
         match self {
             h @ StackObject::HeapObject(_) => match h.as_heap_object().unwrap() {
                 OwnedObjectItem::Box(_) => None,
-                OwnedObjectItem::StructDescriptor(_) => None,
+                OwnedObjectItem::StructDescriptor(d) => d.associated.get(field_name).cloned(),
                 OwnedObjectItem::EnumDescriptor(e) => e.lookup(field_name),
                 OwnedObjectItem::StructInstance(i) => i.lookup(self, field_name, context),
 
@@ -662,6 +678,21 @@ impl StackObject {
         }
     }
 
+    /// registers function of impl block that is called on type itself, like `Point.origin()`
+    pub fn set_associated(&self, function_name: &str, function: Value) -> Result<(), ()> {
+        match self.as_heap_object() {
+            Some(OwnedObjectItem::StructDescriptor(d)) => {
+                d.add_associated(function_name, function);
+                Ok(())
+            }
+            Some(OwnedObjectItem::EnumDescriptor(d)) => {
+                d.add_associated(function_name, function);
+                Ok(())
+            }
+            _ => Err(()),
+        }
+    }
+
     pub fn type_string(&self) -> &'static str {
         match self {
             StackObject::Int(_) => "Int",
@@ -858,7 +889,14 @@ impl Display for OwnedObject {
         match &self.item {
             OwnedObjectItem::ConstantString(ptr) => write!(f, "{}", ptr),
             OwnedObjectItem::Vector(ptr) => write!(f, "Vector {:?}", ptr),
-            OwnedObjectItem::Map(ptr) => write!(f, "Map {:?}", ptr),
+            OwnedObjectItem::Map(ptr) => write!(
+                f,
+                "{{{}}}",
+                ptr.iter()
+                    .map(|(k, v)| format!("{}: {}", k, v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
             OwnedObjectItem::Box(ptr) => write!(f, "box[{}]", ptr.0),
             OwnedObjectItem::Closure(closure) => {
                 write!(
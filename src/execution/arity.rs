@@ -10,19 +10,72 @@ pub enum Arity {
     /// vararg function/method that accepts at least `n` args,
     /// additional args will be put inside vararg
     AtLeast(usize),
+    /// function/method that accepts from `min` to `max` args inclusive,
+    /// e.g. when trailing parameters have defaults
+    Range(usize, usize),
 }
 
 impl Arity {
-    pub fn accepts(&self, args: usize) -> bool {
+    pub fn exact(args: usize) -> Arity {
+        Arity::Exact(args)
+    }
+
+    pub fn at_least(args: usize) -> Arity {
+        Arity::AtLeast(args)
+    }
+
+    /// closed range of accepted argument counts, collapses to [`Arity::Exact`] if `min == max`
+    #[allow(dead_code)]
+    pub fn range(min: usize, max: usize) -> Arity {
+        assert!(min <= max, "invalid arity range {min}..={max}");
+        if min == max {
+            Arity::Exact(min)
+        } else {
+            Arity::Range(min, max)
+        }
+    }
+
+    /// smallest accepted number of args
+    pub fn min(&self) -> usize {
+        match *self {
+            Arity::Exact(i) | Arity::AtLeast(i) | Arity::Range(i, _) => i,
+        }
+    }
+
+    /// largest accepted number of args, `None` for varargs
+    pub fn max(&self) -> Option<usize> {
         match *self {
-            Arity::Exact(i) => i == args,
-            Arity::AtLeast(i) => args >= i,
+            Arity::Exact(i) | Arity::Range(_, i) => Some(i),
+            Arity::AtLeast(_) => None,
         }
     }
 
+    pub fn accepts(&self, args: usize) -> bool {
+        args >= self.min() && !matches!(self.max(), Some(max) if args > max)
+    }
+
     pub fn is_vararg(&self) -> bool {
         matches!(self, Arity::AtLeast(..))
     }
+
+    /// arity of partial application that leaves `holes` arguments unfilled. Filled
+    /// arguments are dropped, while vararg stays open for any number of trailing args
+    pub fn residual(&self, holes: usize) -> Arity {
+        match self {
+            Arity::Exact(_) | Arity::Range(..) => Arity::Exact(holes),
+            Arity::AtLeast(_) => Arity::AtLeast(holes),
+        }
+    }
+
+    /// describes why `got` args are not accepted, telling over- and under-supply apart
+    pub fn mismatch_message(&self, got: usize) -> String {
+        let problem = if got < self.min() {
+            "too few"
+        } else {
+            "too many"
+        };
+        format!("{problem} arguments: expected {self} args but got {got}")
+    }
 }
 
 impl From<Arity> for usize {
@@ -30,6 +83,7 @@ impl From<Arity> for usize {
         match a {
             Arity::Exact(arity) => arity,
             Arity::AtLeast(arity) => arity,
+            Arity::Range(min, _max) => min,
         }
     }
 }
@@ -40,8 +94,9 @@ impl Display for Arity {
             f,
             "{}",
             match self {
-                Arity::Exact(e) => format!("{e} args"),
-                Arity::AtLeast(args) => format!("at least {args} args"),
+                Arity::Exact(e) => format!("{e}"),
+                Arity::AtLeast(args) => format!("at least {args}"),
+                Arity::Range(min, max) => format!("{min} to {max}"),
             }
         )
     }
@@ -54,6 +109,7 @@ impl Add<usize> for Arity {
         match self {
             Arity::Exact(i) => Arity::Exact(i + rhs),
             Arity::AtLeast(i) => Arity::AtLeast(i + rhs),
+            Arity::Range(min, max) => Arity::Range(min + rhs, max + rhs),
         }
     }
 }
@@ -65,6 +121,91 @@ impl Sub<usize> for Arity {
         match self {
             Arity::Exact(i) => Arity::Exact(i - rhs),
             Arity::AtLeast(i) => Arity::AtLeast(i - rhs),
+            //optional args may cover removed ones, so only upper bound has to fit
+            Arity::Range(min, max) => Arity::range(min.saturating_sub(rhs), max - rhs),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Arity;
+
+    #[test]
+    fn exact_arity_accepts_only_its_count() {
+        let arity = Arity::exact(2);
+        assert!(!arity.accepts(1));
+        assert!(arity.accepts(2));
+        assert!(!arity.accepts(3));
+        assert_eq!((arity.min(), arity.max()), (2, Some(2)));
+    }
+
+    #[test]
+    fn at_least_arity_has_no_upper_bound() {
+        let arity = Arity::at_least(1);
+        assert!(!arity.accepts(0));
+        assert!(arity.accepts(1));
+        assert!(arity.accepts(100));
+        assert_eq!((arity.min(), arity.max()), (1, None));
+        assert!(Arity::at_least(0).accepts(0));
+    }
+
+    #[test]
+    fn range_arity_accepts_bounds_inclusively() {
+        let arity = Arity::range(1, 3);
+        assert!(!arity.accepts(0));
+        assert!(arity.accepts(1));
+        assert!(arity.accepts(3));
+        assert!(!arity.accepts(4));
+        assert_eq!((arity.min(), arity.max()), (1, Some(3)));
+    }
+
+    #[test]
+    fn degenerate_range_is_exact() {
+        assert_eq!(Arity::range(2, 2), Arity::Exact(2));
+    }
+
+    #[test]
+    fn subtracting_from_range_keeps_lower_bound_non_negative() {
+        assert_eq!(Arity::range(0, 2) - 1, Arity::range(0, 1));
+        assert_eq!(Arity::range(0, 1) - 1, Arity::Exact(0));
+        assert_eq!(Arity::range(2, 4) - 1, Arity::range(1, 3));
+    }
+
+    #[test]
+    fn arity_should_display_accepted_counts() {
+        assert_eq!(Arity::exact(2).to_string(), "2");
+        assert_eq!(Arity::at_least(1).to_string(), "at least 1");
+        assert_eq!(Arity::range(1, 3).to_string(), "1 to 3");
+    }
+
+    #[test]
+    fn residual_arity_keeps_only_holes() {
+        //f(_, 1, _) of 3-arg function
+        assert_eq!(Arity::exact(3).residual(2), Arity::Exact(2));
+        //defaults can not be told apart from holes, so all remaining args are required
+        assert_eq!(Arity::range(1, 3).residual(1), Arity::Exact(1));
+        //vararg region accepts any number of args even if partially filled
+        assert_eq!(Arity::at_least(2).residual(1), Arity::AtLeast(1));
+    }
+
+    #[test]
+    fn mismatch_message_tells_too_few_from_too_many() {
+        assert_eq!(
+            Arity::exact(2).mismatch_message(1),
+            "too few arguments: expected 2 args but got 1"
+        );
+        assert_eq!(
+            Arity::exact(2).mismatch_message(3),
+            "too many arguments: expected 2 args but got 3"
+        );
+        assert_eq!(
+            Arity::at_least(2).mismatch_message(0),
+            "too few arguments: expected at least 2 args but got 0"
+        );
+        assert_eq!(
+            Arity::range(1, 3).mismatch_message(4),
+            "too many arguments: expected 1 to 3 args but got 4"
+        );
+    }
+}
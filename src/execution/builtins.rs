@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, io::Write};
 
 ///
 /// contract: all builtin functions may change vm state, but they should never touch VM's buitin_map as it may be aliased
@@ -30,7 +30,7 @@ impl Display for BuiltinError {
             "{}",
             match self {
                 BuiltinError::ArityMismatch { provided, expected } =>
-                    format!("expected {} args but got {}", expected, provided),
+                    expected.mismatch_message(*provided),
                 BuiltinError::Other(e) => e.clone(),
             }
         )
@@ -141,6 +141,14 @@ impl BuiltinMap {
             })
     }
 
+    /// names of all builtin functions and values
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.functions
+            .keys()
+            .chain(self.builtin_values.keys())
+            .map(String::as_str)
+    }
+
     pub fn get_builtin_name(&self, idx: usize) -> Option<&str> {
         self.functions.get_index(idx).map(|(k, _v)| k.as_str())
     }
@@ -278,7 +286,7 @@ pub fn builtin_factory() -> BuiltinMap {
             .collect::<Vec<String>>()
             .join(" ");
 
-        println!("{}", s);
+        writeln!(vm.output, "{}", s).map_err(|e| BuiltinError::Other(e.to_string()))?;
 
         Ok(Default::default())
     });
@@ -30,6 +30,10 @@ pub enum Opcode {
     StoreField(u16),
     LoadFieldByIndex(u16),
     StoreFieldByIndex(u16),
+    StoreAssociated(u16),
+
+    CheckLength(u16),
+    LoadElement(u16),
 
     NewBox,
     LoadBox,
@@ -64,6 +68,12 @@ pub enum Opcode {
     TestProperty(u16),
 
     LogicalNot,
+    Negate,
+    BitwiseNot,
+
+    CastToInt,
+    CastToFloat,
+    CastToString,
 
     JumpIfFalseOrPop(u16),
     JumpIfTrueOrPop(u16),
@@ -71,14 +81,22 @@ pub enum Opcode {
     JumpAbsolute(u16),
     Pop(u16),
 
+    PushHandler(u16),
+    PopHandler,
+
     Call(u16),
 
     MakeList(u16),
+    MakeMap(u16),
+    LoadIndex,
+    LoadSlice,
+    StoreIndex,
     Return,
 
     Import(u16),
 
     Nop,
+    FailAssertion,
     Assert, //SwapStack(u8, u8),
             //ExtendArg1(u16),
             //ExtendDouble(u8, u8)
@@ -108,6 +126,59 @@ impl Chunk {
         self.code.append(&mut blob.code);
         self.opcode_to_line.append(&mut blob.indices);
     }
+
+    /// checks that operands of instructions refer to existing constants, names, imports and
+    /// instructions and that code ends with `Return`, so that VM never reads out of bounds.
+    /// Chunks of functions stored among constants are checked too
+    pub fn validate(&self) -> Result<(), String> {
+        let name = self.name.get_string().unwrap_or("<unnamed>");
+
+        if self.opcode_to_line.len() != self.code.len() {
+            return Err(format!(
+                "chunk {} has {} instructions but {} line numbers",
+                name,
+                self.code.len(),
+                self.opcode_to_line.len()
+            ));
+        }
+
+        if self.code.last() != Some(&Opcode::Return) {
+            return Err(format!("chunk {} does not end with Return", name));
+        }
+
+        for (i, opcode) in self.code.iter().enumerate() {
+            let (operand, limit, what) = match *opcode {
+                Opcode::LoadConst(idx) => (idx as usize, self.constants.len(), "constant"),
+                Opcode::LoadGlobal(idx)
+                | Opcode::StoreGLobal(idx)
+                | Opcode::LoadField(idx)
+                | Opcode::StoreField(idx)
+                | Opcode::StoreAssociated(idx)
+                | Opcode::TestProperty(idx) => (idx as usize, self.global_names.len(), "name"),
+                Opcode::Import(idx) => (idx as usize, self.import_names.len(), "import"),
+                Opcode::JumpIfFalseOrPop(delta)
+                | Opcode::JumpIfTrueOrPop(delta)
+                | Opcode::JumpRelative(delta)
+                | Opcode::PushHandler(delta) => {
+                    (i + delta as usize, self.code.len(), "jump target")
+                }
+                Opcode::JumpAbsolute(idx) => (idx as usize, self.code.len(), "jump target"),
+                _ => continue,
+            };
+
+            if operand >= limit {
+                return Err(format!(
+                    "{} {} of instruction #{} {} in chunk {} is out of range (chunk has {})",
+                    what, operand, i, opcode, name, limit
+                ));
+            }
+        }
+
+        self.constants
+            .iter()
+            .filter_map(|constant| constant.unwrap_function())
+            .try_for_each(|chunk| chunk.validate())
+    }
 }
 
 impl Display for Chunk {
@@ -120,6 +191,7 @@ impl Display for Chunk {
                 match &self.name.kind {
                     TokenKind::Name(..) => self.name.get_string().unwrap(),
                     TokenKind::Arrow => "<anon function>",
+                    TokenKind::Catch => "<catch handler>",
                     _ => panic!(),
                 },
                 self.name.position
@@ -190,6 +262,8 @@ mod chunk_pretty_printer {
 
                     Opcode::StoreField(idx) => pretty_with_global!(*idx),
 
+                    Opcode::StoreAssociated(idx) => pretty_with_global!(*idx),
+
                     Opcode::TestProperty(idx) => pretty_with_global!(*idx),
 
                     Opcode::StoreGLobal(idx) => pretty_with_global!(*idx),
@@ -219,6 +293,8 @@ mod chunk_pretty_printer {
 
                     Opcode::JumpAbsolute(idx) => pretty_argument!(*idx as usize),
 
+                    Opcode::PushHandler(delta) => pretty_argument!(i + *delta as usize),
+
                     any_other => {
                         format!("{}", any_other)
                     }
@@ -323,3 +399,84 @@ mod chunk_pretty_printer {
         raw_str_ref[idx] = c;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Chunk, Opcode};
+    use crate::data::gc::GC;
+    use crate::execution::arity::Arity;
+    use crate::execution::module::Module;
+    use crate::parsing::lexer::{Index, Token, TokenKind};
+
+    fn chunk(code: Vec<Opcode>) -> Chunk {
+        let name = Token {
+            kind: TokenKind::Name("f".to_string()),
+            position: Index(0, 0, 0),
+        };
+        let mut chunk = Chunk::new(name, Module::from_dot_notation("test"), Arity::Exact(0));
+        chunk.opcode_to_line = vec![1; code.len()];
+        chunk.code = code;
+        chunk
+    }
+
+    #[test]
+    fn well_formed_chunk_should_be_valid() {
+        let mut valid = chunk(vec![
+            Opcode::LoadGlobal(0),
+            Opcode::JumpIfFalseOrPop(2),
+            Opcode::LoadImmediateInt(1),
+            Opcode::JumpAbsolute(4),
+            Opcode::Return,
+        ]);
+        valid.global_names.push("x".to_string());
+        assert_eq!(valid.validate(), Ok(()));
+    }
+
+    #[test]
+    fn corrupted_chunks_should_be_rejected() {
+        let error = |chunk: Chunk| chunk.validate().unwrap_err();
+
+        assert_eq!(
+            error(chunk(vec![Opcode::LoadConst(0), Opcode::Return])),
+            "constant 0 of instruction #0 LoadConst(0) in chunk f is out of range (chunk has 0)"
+        );
+        assert_eq!(
+            error(chunk(vec![Opcode::LoadField(3), Opcode::Return])),
+            "name 3 of instruction #0 LoadField(3) in chunk f is out of range (chunk has 0)"
+        );
+        assert_eq!(
+            error(chunk(vec![Opcode::Import(0), Opcode::Return])),
+            "import 0 of instruction #0 Import(0) in chunk f is out of range (chunk has 0)"
+        );
+        assert_eq!(
+            error(chunk(vec![Opcode::Nop, Opcode::JumpRelative(2), Opcode::Return])),
+            "jump target 3 of instruction #1 JumpRelative(2) in chunk f is out of range (chunk has 3)"
+        );
+        assert_eq!(
+            error(chunk(vec![Opcode::JumpAbsolute(7), Opcode::Return])),
+            "jump target 7 of instruction #0 JumpAbsolute(7) in chunk f is out of range (chunk has 2)"
+        );
+        assert_eq!(
+            error(chunk(vec![Opcode::LoadNothing])),
+            "chunk f does not end with Return"
+        );
+
+        let mut missing_lines = chunk(vec![Opcode::LoadNothing, Opcode::Return]);
+        missing_lines.opcode_to_line.pop();
+        assert_eq!(
+            error(missing_lines),
+            "chunk f has 2 instructions but 1 line numbers"
+        );
+    }
+
+    #[test]
+    fn functions_among_constants_should_be_checked() {
+        let mut gc = unsafe { GC::default_gc() };
+        let mut outer = chunk(vec![Opcode::LoadConst(0), Opcode::Return]);
+        outer.constants.push(gc.store(chunk(vec![Opcode::Pop(1)])));
+        assert_eq!(
+            outer.validate().unwrap_err(),
+            "chunk f does not end with Return"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+
+use crate::data::objects::Value;
+
+use super::vm::{InterpretError, VM};
+
+/// where paused program is going to continue from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    /// source line of next instruction
+    pub line: usize,
+    /// number of active calls, 1 while running top-level code
+    pub call_depth: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    Paused(Position),
+    Finished(Value),
+}
+
+/// runs program one instruction at a time. Program is executed the same way as by
+/// [`VM::run`], including garbage collection between instructions
+pub struct Stepper<'vm, 'gc, 'builtins> {
+    vm: &'vm mut VM<'gc, 'builtins>,
+    ip: usize,
+    chunk: Value,
+    breakpoints: HashSet<usize>,
+    result: Option<Value>,
+}
+
+#[allow(dead_code)]
+impl<'vm, 'gc, 'builtins> Stepper<'vm, 'gc, 'builtins> {
+    pub fn new(vm: &'vm mut VM<'gc, 'builtins>, entry_point: Value) -> Self {
+        vm.enter(entry_point.clone());
+        Stepper {
+            vm,
+            ip: 0,
+            chunk: entry_point,
+            breakpoints: HashSet::new(),
+            result: None,
+        }
+    }
+
+    pub fn vm(&mut self) -> &mut VM<'gc, 'builtins> {
+        self.vm
+    }
+
+    /// pauses [`Stepper::resume`] before first instruction of every run of given line
+    pub fn add_breakpoint(&mut self, line: usize) {
+        self.breakpoints.insert(line);
+    }
+
+    /// returns false if there was no breakpoint at given line
+    pub fn remove_breakpoint(&mut self, line: usize) -> bool {
+        self.breakpoints.remove(&line)
+    }
+
+    pub fn state(&self) -> Step {
+        match &self.result {
+            Some(value) => Step::Finished(value.clone()),
+            None => Step::Paused(self.position()),
+        }
+    }
+
+    fn position(&self) -> Position {
+        let chunk = self.chunk.unwrap_function().unwrap();
+        Position {
+            line: chunk.opcode_to_line.get(self.ip).copied().unwrap_or(0),
+            call_depth: self.vm.call_stack.len(),
+        }
+    }
+
+    /// executes single instruction
+    pub fn step(&mut self) -> Result<Step, InterpretError> {
+        if self.result.is_none() {
+            self.result = self.vm.step_instruction(&mut self.ip, &mut self.chunk)?;
+        }
+        Ok(self.state())
+    }
+
+    /// executes instructions until program finishes or reaches line with breakpoint
+    /// from another line
+    pub fn resume(&mut self) -> Result<Step, InterpretError> {
+        loop {
+            let line_before = self.position().line;
+            if let Step::Paused(position) = self.step()? {
+                if position.line != line_before && self.breakpoints.contains(&position.line) {
+                    return Ok(Step::Paused(position));
+                }
+            } else {
+                return Ok(self.state());
+            }
+        }
+    }
+}
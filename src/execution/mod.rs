@@ -1,5 +1,6 @@
 pub mod arity;
 pub mod builtins;
 pub mod chunk;
+pub mod debugger;
 pub mod module;
 pub mod vm;
@@ -4,9 +4,12 @@ use std::{
 };
 
 use crate::{
-    compile::compiler::Compiler,
+    compile::{checks::LintConfig, compiler::Compiler},
     data::{gc::GC, objects::Value},
-    parsing,
+    parsing::{
+        self,
+        ast::{Program, Stmt},
+    },
 };
 
 use super::vm::VM;
@@ -68,13 +71,9 @@ fn normalize_string(s: String) -> String {
         .join("\n")
 }
 
-pub fn compile_program(
-    program: String,
-    module: &Module,
-    vm: &mut VM,
-) -> Result<Value, Box<dyn Error>> {
-    let file_content = normalize_string(program);
-    let tokens = parsing::lexer::tokenize(&file_content)?;
+/// parses source already passed through [`normalize_string`], token offsets point into it
+fn parse_program(source: &str) -> Result<Program, Box<dyn Error>> {
+    let tokens = parsing::lexer::tokenize(source)?;
 
     #[cfg(feature = "print-tokens")]
     {
@@ -91,15 +90,68 @@ pub fn compile_program(
     let statements: Vec<parsing::ast::Stmt> = program_parser::program(tokens.as_slice())
         .map_err(|e| format!("{:?}\n{:?}", e, tokens[e.location]))?;
 
+    Ok(statements)
+}
+
+/// source of program reformatted by [`printer`](parsing::printer)
+pub fn format_program(program: String) -> Result<String, Box<dyn Error>> {
+    let source = normalize_string(program);
+    Ok(parsing::printer::print_program(&parse_program(&source)?))
+}
+
+pub fn compile_program(
+    program: String,
+    module: &Module,
+    vm: &mut VM,
+) -> Result<Value, Box<dyn Error>> {
+    let source = normalize_string(program);
+    compile_statements(parse_program(&source)?, &source, module, vm)
+}
+
+/// compiles source that consists of exactly one expression, value of that expression
+/// is returned when resulting chunk is run
+pub fn compile_expression(
+    source: String,
+    module: &Module,
+    vm: &mut VM,
+) -> Result<Value, Box<dyn Error>> {
+    let source = normalize_string(source);
+    let statements = parse_program(&source)?;
+    match statements.as_slice() {
+        [Stmt::Expression(..)] => compile_statements(statements, &source, module, vm),
+        [_] => Err("expected expression, got statement".into()),
+        other => Err(format!("expected single expression, got {} statements", other.len()).into()),
+    }
+}
+
+fn compile_statements(
+    statements: Program,
+    source: &str,
+    module: &Module,
+    vm: &mut VM,
+) -> Result<Value, Box<dyn Error>> {
+    let lint_config = LintConfig {
+        builtins: vm.builtins.names().map(str::to_string).collect(),
+        ..vm.lints.clone()
+    };
+    for warning in crate::compile::checks::lint(&statements, &lint_config) {
+        eprintln!("warning: {}", warning);
+    }
+
     let (statements, annotations) = crate::compile::checks::check_optimize(statements)?;
 
     #[cfg(feature = "print-ast")]
-    println!("{:?}", statements);
+    {
+        println!("{:?}", statements);
+        println!("{}", parsing::printer::print_program(&statements));
+    }
 
     #[cfg(feature = "print-annotations")]
-    println!("ANNOTATIONS:\n{annotations:?}");
+    println!("ANNOTATIONS:\n{}", annotations.dump());
 
-    let pointer = Compiler::compile_module(&statements, annotations, module.clone(), vm.gc)?;
+    let pointer =
+        Compiler::compile_module(&statements, source, annotations, module.clone(), vm.gc)?;
+    pointer.unwrap_function().unwrap().validate()?;
 
     vm.maybe_create_module(module);
 
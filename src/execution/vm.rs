@@ -1,9 +1,14 @@
-use crate::data::gc::GC;
-use crate::data::objects::{Closure, StackObject, VVec, Value, ValueBox};
+use crate::compile::checks::LintConfig;
+use crate::data::gc::{HeapReport, GC};
+use crate::data::objects::{Closure, StackObject, VMap, VVec, Value, ValueBox};
 use crate::data::value_ops::{self, cast_binary, numeric_cast, NumberCastResult};
-use crate::execution::chunk::Opcode;
+use crate::execution::chunk::{Chunk, Opcode};
+use crate::parsing::lexer::{Index, Token, TokenKind};
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::time::{Duration, Instant};
 
 use super::arity::Arity;
 use super::builtins::BuiltinMap;
@@ -12,14 +17,29 @@ use super::module::Module;
 const DEFAULT_MAX_STACK_SIZE: usize = 4 * 1024 * 1024 / std::mem::size_of::<StackObject>();
 //4MB
 
+/// how often (in executed instructions) [`VM::run_with_timeout`] checks its budget, power of two
+const BUDGET_CHECK_INTERVAL: u64 = 1024;
+
+/// global variables of every loaded module
+type Globals = HashMap<Module, HashMap<String, Value>>;
+
 pub struct VM<'gc, 'builtins> {
     pub(super) stack: Vec<Value>,
     pub(super) call_stack: Vec<CallStackValue>,
-    pub(super) loaded_modules: HashMap<Module, HashMap<String, Value>>,
+    pub(super) loaded_modules: Globals,
+    /// saved globals, see [`VM::push_snapshot`]. Part of GC roots
+    snapshots: Vec<Globals>,
+    /// handlers of `try` expressions being evaluated, innermost last
+    handlers: Vec<ErrorHandler>,
     locals_offset: usize,
     stack_max_size: usize,
+    coercion: CoercionPolicy,
+    /// lints reported when compiling programs for this VM
+    pub(crate) lints: LintConfig,
     pub gc: &'gc mut GC,
     pub(crate) builtins: &'builtins BuiltinMap,
+    /// destination of `print`, stdout by default
+    pub(crate) output: Box<dyn Write>,
 }
 
 pub struct CallStackValue {
@@ -29,8 +49,49 @@ pub struct CallStackValue {
     return_stack_size: usize,
 }
 
+/// how arithmetic operators treat `Bool` operands
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CoercionPolicy {
+    /// `Bool` operand of arithmetic operator is a type error
+    #[default]
+    Strict,
+    /// `true` and `false` are used as `1` and `0`
+    Permissive,
+}
+
+/// where to continue once error is raised inside `try`, stacks are cut down to the state
+/// they had when handler was installed, with handler function on top
+struct ErrorHandler {
+    call_depth: usize,
+    stack_size: usize,
+    locals_offset: usize,
+    chunk: StackObject,
+    ip: usize,
+}
+
 type Result<T> = std::result::Result<T, InterpretError>;
 
+/// limits for [`VM::run_with_timeout`], execution stops when any of them is exceeded.
+/// Limits are checked every [`BUDGET_CHECK_INTERVAL`] instructions,
+/// so program may overrun them by less than one interval
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExecutionBudget {
+    pub max_instructions: Option<u64>,
+    pub max_duration: Option<Duration>,
+}
+
+impl ExecutionBudget {
+    fn is_exhausted(&self, executed_instructions: u64, started_at: Instant) -> bool {
+        self.max_instructions
+            .map(|max| executed_instructions >= max)
+            .unwrap_or(false)
+            || self
+                .max_duration
+                .map(|max| started_at.elapsed() >= max)
+                .unwrap_or(false)
+    }
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct InterpretError {
@@ -44,9 +105,11 @@ pub struct InterpretError {
 pub enum InterpretErrorKind {
     StackUnderflow,
     ZeroDivision,
+    IntegerOverflow,
     OperandIndexing,
     JumpBounds,
     AssertionFailure,
+    AssertionFailureWithMessage { message: String },
     StackOverflow,
     TypeError { message: String },
     MissedReturn,
@@ -54,7 +117,62 @@ pub enum InterpretErrorKind {
     NativeError { message: String },
     AttributeError { object: Value, missed_field: String },
     IndexAttributeError { object: Value, missed_idx: usize },
+    KeyError { key: Value },
+    IndexOutOfBounds { index: i64, length: usize },
     ImportError { message: String },
+    Timeout,
+}
+
+impl InterpretErrorKind {
+    /// whether error may be handled by `try` expression. Exceeded limits and errors
+    /// caused by malformed bytecode always stop the program
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            self,
+            InterpretErrorKind::StackUnderflow
+                | InterpretErrorKind::OperandIndexing
+                | InterpretErrorKind::JumpBounds
+                | InterpretErrorKind::MissedReturn
+                | InterpretErrorKind::StackOverflow
+                | InterpretErrorKind::Timeout
+        )
+    }
+}
+
+/// name of error kind followed by its details, this is what `try` handlers receive
+impl Display for InterpretErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use InterpretErrorKind::*;
+        match self {
+            AssertionFailureWithMessage { message } => write!(f, "AssertionFailure: {}", message),
+            TypeError { message } => write!(f, "TypeError: {}", message),
+            NativeError { message } => write!(f, "NativeError: {}", message),
+            ImportError { message } => write!(f, "ImportError: {}", message),
+            NameError { name } => write!(f, "NameError: {} is not defined", name),
+            AttributeError {
+                object,
+                missed_field,
+            } => write!(
+                f,
+                "AttributeError: {} has no field {}",
+                object.type_string(),
+                missed_field
+            ),
+            IndexAttributeError { object, missed_idx } => write!(
+                f,
+                "AttributeError: {} has no field {}",
+                object.type_string(),
+                missed_idx
+            ),
+            KeyError { key } => write!(f, "KeyError: {}", key),
+            IndexOutOfBounds { index, length } => write!(
+                f,
+                "IndexOutOfBounds: index {} is out of bounds for length {}",
+                index, length
+            ),
+            other => write!(f, "{:?}", other),
+        }
+    }
 }
 
 enum InstructionExecution {
@@ -74,13 +192,35 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
             stack: Vec::new(),
             call_stack: Vec::new(),
             loaded_modules: Default::default(),
+            snapshots: Vec::new(),
+            handlers: Vec::new(),
             locals_offset: 0,
             gc,
             stack_max_size: DEFAULT_MAX_STACK_SIZE,
+            coercion: CoercionPolicy::default(),
+            lints: LintConfig::default(),
             builtins,
+            output: Box::new(std::io::stdout()),
         }
     }
 
+    /// redirects program output (e.g. to capture it in tests), returns previous sink
+    #[allow(dead_code)]
+    pub fn set_output(&mut self, output: Box<dyn Write>) -> Box<dyn Write> {
+        std::mem::replace(&mut self.output, output)
+    }
+
+    /// changes how `Bool` operands of arithmetic are treated, returns previous policy
+    #[allow(dead_code)]
+    pub fn set_coercion_policy(&mut self, policy: CoercionPolicy) -> CoercionPolicy {
+        std::mem::replace(&mut self.coercion, policy)
+    }
+
+    /// changes lints reported by further compilations, returns previous config
+    pub fn set_lint_config(&mut self, config: LintConfig) -> LintConfig {
+        std::mem::replace(&mut self.lints, config)
+    }
+
     #[cfg(test)]
     pub fn override_stack_limit(&mut self, new_limit: usize) -> usize {
         let old_stack_size = self.stack_max_size;
@@ -91,6 +231,7 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
     pub fn reset_stacks(&mut self) {
         self.call_stack.clear();
         self.stack.clear();
+        self.handlers.clear();
         self.locals_offset = 0;
     }
 
@@ -108,114 +249,307 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
         self.loaded_modules.entry(module.clone()).or_default();
     }
 
+    /// remembers globals of all loaded modules so that later evaluation can be rolled back
+    /// with [`VM::restore_snapshot`]. Snapshots nest. Values they hold stay alive, but heap
+    /// objects are shared, so mutations of lists, maps or instances are not rolled back
+    #[allow(dead_code)]
+    pub fn push_snapshot(&mut self) {
+        self.snapshots.push(self.loaded_modules.clone());
+    }
+
+    /// brings globals (and set of loaded modules) back to the latest snapshot and discards it,
+    /// returns false if there was no snapshot
+    #[allow(dead_code)]
+    pub fn restore_snapshot(&mut self) -> bool {
+        match self.snapshots.pop() {
+            Some(globals) => {
+                self.loaded_modules = globals;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// drops the latest snapshot keeping current globals, returns false if there was no snapshot
+    #[allow(dead_code)]
+    pub fn discard_snapshot(&mut self) -> bool {
+        self.snapshots.pop().is_some()
+    }
+
+    /// top-level bindings of loaded `module` with their current values, in no particular order.
+    /// Yields nothing if module was not loaded
+    #[allow(dead_code)]
+    pub fn globals(&self, module: &Module) -> impl Iterator<Item = (&str, &Value)> {
+        self.loaded_modules
+            .get(module)
+            .into_iter()
+            .flat_map(|globals| globals.iter().map(|(name, value)| (name.as_str(), value)))
+    }
+
     pub fn run(&mut self, entry_point: StackObject) -> Result<StackObject> {
+        self.run_with_budget(entry_point, None)
+    }
+
+    /// compiles and runs source of a single expression, returning its value.
+    /// Errors of every stage are reported the same way as when running a file
+    #[allow(dead_code)]
+    pub fn eval_expr(
+        &mut self,
+        source: &str,
+    ) -> std::result::Result<StackObject, Box<dyn std::error::Error>> {
+        use crate::execution::module;
+
+        let module = Module::from_dot_notation("`eval`");
+        let pointer = module::compile_expression(source.to_string(), &module, self)?;
+        module::exec_with_error_printing(self, pointer, source)
+    }
+
+    /// calls global `name` of already loaded `module` with given arguments and returns result.
+    /// Call is made from generated chunk that keeps arguments as its constants,
+    /// so they are rooted for as long as the call runs
+    #[allow(dead_code)]
+    pub fn call_function(
+        &mut self,
+        module: &Module,
+        name: &str,
+        args: VVec,
+    ) -> Result<StackObject> {
+        let token = Token {
+            kind: TokenKind::Name(format!("`call {}`", name)),
+            position: Index(0, 0, 0),
+        };
+        let mut chunk = Chunk::new(token, module.clone(), Arity::Exact(0));
+        chunk.global_names.push(name.to_string());
+        chunk.code.push(Opcode::LoadGlobal(0));
+        for (i, arg) in args.into_iter().enumerate() {
+            chunk.constants.push(arg);
+            chunk.code.push(Opcode::LoadConst(i as u16));
+        }
+        chunk.code.push(Opcode::Call(chunk.constants.len() as u16));
+        chunk.code.push(Opcode::Return);
+        chunk.opcode_to_line = vec![0; chunk.code.len()];
+
+        let pointer = self.gc.store(chunk);
+        if !self.loaded_modules.contains_key(module) {
+            return Err(InterpretError {
+                opcode_index: 0,
+                chunk: pointer,
+                kind: InterpretErrorKind::NameError {
+                    name: name.to_string(),
+                },
+            });
+        }
+        self.run(pointer)
+    }
+
+    /// same as [`VM::run`], but fails with [`InterpretErrorKind::Timeout`] once budget is exceeded
+    #[allow(dead_code)]
+    pub fn run_with_timeout(
+        &mut self,
+        entry_point: StackObject,
+        budget: ExecutionBudget,
+    ) -> Result<StackObject> {
+        self.run_with_budget(entry_point, Some(budget))
+    }
+
+    fn run_with_budget(
+        &mut self,
+        entry_point: StackObject,
+        budget: Option<ExecutionBudget>,
+    ) -> Result<StackObject> {
         use InterpretErrorKind::*;
-        self.reset_stacks();
+        self.enter(entry_point.clone());
+        let started_at = Instant::now();
+        let mut executed_instructions: u64 = 0;
         let mut ip = 0;
         let mut current_chunk = entry_point;
 
+        loop {
+            if let Some(budget) = &budget {
+                executed_instructions += 1;
+                if executed_instructions & (BUDGET_CHECK_INTERVAL - 1) == 0
+                    && budget.is_exhausted(executed_instructions, started_at)
+                {
+                    return Err(InterpretError {
+                        opcode_index: ip,
+                        chunk: current_chunk,
+                        kind: Timeout,
+                    });
+                }
+            }
+
+            if let Some(value) = self.step_instruction(&mut ip, &mut current_chunk)? {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// prepares stacks for running `entry_point` from its first instruction
+    pub(super) fn enter(&mut self, entry_point: StackObject) {
+        self.reset_stacks();
         self.call_stack.push(CallStackValue {
-            return_chunk: current_chunk.clone(),
+            return_chunk: entry_point,
             return_ip: 0,
             return_locals_offset: 0,
             return_stack_size: usize::MAX,
         });
+    }
+
+    /// executes instruction at `ip` of `current_chunk` and moves both to next instruction,
+    /// collecting garbage if needed. Returns value of entry point once it terminates
+    #[inline(always)]
+    pub(super) fn step_instruction(
+        &mut self,
+        ip: &mut usize,
+        current_chunk: &mut StackObject,
+    ) -> Result<Option<StackObject>> {
+        use InterpretErrorKind::*;
 
         macro_rules! runtime_error {
             ($e:expr) => {
                 InterpretError {
-                    opcode_index: ip,
-                    chunk: current_chunk,
+                    opcode_index: *ip,
+                    chunk: current_chunk.clone(),
                     kind: $e,
                 }
             };
         }
 
-        macro_rules! checked_stack_pop {
-            () => {{
-                self.stack.pop().ok_or(InterpretError {
-                    opcode_index: ip,
-                    chunk: current_chunk.clone(),
-                    kind: StackUnderflow,
-                })
-            }};
+        if *ip >= current_chunk.unwrap_function().unwrap().code.len() {
+            return Err(InterpretError {
+                opcode_index: *ip - 1,
+                chunk: current_chunk.clone(),
+                kind: MissedReturn,
+            });
         }
 
-        while ip < current_chunk.unwrap_function().unwrap().code.len() {
-            #[cfg(feature = "print-execution")]
-            print!("{} => ", current_chunk.unwrap_function().unwrap().code[ip]);
+        #[cfg(feature = "print-execution")]
+        print!("{} => ", current_chunk.unwrap_function().unwrap().code[*ip]);
 
-            let jump = self.execute_instruction(ip, &current_chunk)?;
+        let jump = match self.execute_instruction(*ip, current_chunk) {
+            Ok(jump) => jump,
+            Err(error) => self.handle_error(error)?,
+        };
 
-            #[cfg(feature = "print-execution")]
-            {
-                use crate::data::objects::pretty_format;
-                print!("[");
-                for item in &self.stack[..self.locals_offset] {
-                    print!("{} ", pretty_format(item, self));
-                }
-                print!("| ");
-                for item in &self.stack[self.locals_offset..] {
-                    print!("{} ", pretty_format(item, self));
-                }
-                println!("]");
+        #[cfg(feature = "print-execution")]
+        {
+            use crate::data::objects::pretty_format;
+            print!("[");
+            for item in &self.stack[..self.locals_offset] {
+                print!("{} ", pretty_format(item, self));
             }
+            print!("| ");
+            for item in &self.stack[self.locals_offset..] {
+                print!("{} ", pretty_format(item, self));
+            }
+            println!("]");
+        }
 
-            match jump {
-                InstructionExecution::NextInstruction => {
-                    ip += 1;
-                }
-
-                InstructionExecution::LocalJump(idx) => {
-                    ip = idx;
-                }
+        match jump {
+            InstructionExecution::NextInstruction => {
+                *ip += 1;
+            }
 
-                InstructionExecution::EnterChunk(chunk) => {
-                    ip = 0;
-                    current_chunk = chunk;
-                }
+            InstructionExecution::LocalJump(idx) => {
+                *ip = idx;
+            }
 
-                InstructionExecution::CrossChunkJump {
-                    new_chunk_id,
-                    new_ip,
-                } => {
-                    ip = new_ip;
-                    current_chunk = new_chunk_id;
-                }
+            InstructionExecution::EnterChunk(chunk) => {
+                *ip = 0;
+                *current_chunk = chunk;
+            }
 
-                InstructionExecution::Termination => {
-                    let value = checked_stack_pop!()?;
-                    //return immediately, without possibly triggering gc
-                    return Ok(value);
-                }
+            InstructionExecution::CrossChunkJump {
+                new_chunk_id,
+                new_ip,
+            } => {
+                *ip = new_ip;
+                *current_chunk = new_chunk_id;
             }
 
-            if self.stack.len() > self.stack_max_size || self.call_stack.len() > self.stack_max_size
-            {
-                return Err(runtime_error!(StackOverflow));
-                //TODO include last stack frame?
+            InstructionExecution::Termination => {
+                let value = self.stack.pop().ok_or(runtime_error!(StackUnderflow))?;
+                //return immediately, without possibly triggering gc
+                return Ok(Some(value));
             }
-            if self.gc.needs_collection() {
-                unsafe {
-                    self.gc.mark_and_sweep(
-                        self.stack
-                            .iter()
-                            .chain(self.loaded_modules.values().flat_map(|v| v.values())),
-                        &*self.call_stack,
-                    );
-                }
+        }
+
+        if self.stack.len() > self.stack_max_size || self.call_stack.len() > self.stack_max_size {
+            return Err(runtime_error!(StackOverflow));
+            //TODO include last stack frame?
+        }
+        if self.gc.needs_collection() {
+            unsafe {
+                self.collect_garbage(&[]);
             }
         }
+        Ok(None)
+    }
 
-        if ip == current_chunk.unwrap_function().unwrap().code.len() {
-            return Err(InterpretError {
-                opcode_index: ip - 1,
-                chunk: current_chunk,
-                kind: InterpretErrorKind::MissedReturn,
-            });
+    /// passes error to innermost `try` handler as a string describing it,
+    /// returns error back if it cannot be caught
+    fn handle_error(&mut self, error: InterpretError) -> Result<InstructionExecution> {
+        if !error.kind.is_catchable() {
+            return Err(error);
         }
-        //function will always terminate through InstructionExecution::Termination
-        unreachable!()
+        let handler = match self.handlers.pop() {
+            Some(handler) => handler,
+            None => return Err(error),
+        };
+
+        self.load_stacks((
+            handler.call_depth,
+            handler.stack_size,
+            handler.locals_offset,
+        ));
+        let description = self.gc.new_string(&error.kind.to_string());
+        self.stack.push(description);
+        Ok(InstructionExecution::CrossChunkJump {
+            new_chunk_id: handler.chunk,
+            new_ip: handler.ip,
+        })
+    }
+
+    /// frees objects that are not reachable from VM state or `extra_roots`
+    unsafe fn collect_garbage(&mut self, extra_roots: &[StackObject]) {
+        self.gc.mark_and_sweep(
+            self.stack
+                .iter()
+                .chain(self.loaded_modules.values().flat_map(|v| v.values()))
+                .chain(
+                    self.snapshots
+                        .iter()
+                        .flat_map(|s| s.values().flat_map(|v| v.values())),
+                )
+                .chain(extra_roots),
+            &self.call_stack,
+        );
+    }
+
+    /// collects garbage and counts heap objects that stay alive.
+    ///
+    /// # Safety
+    /// values held outside of VM (such as entry point of finished program) must be passed
+    /// in `kept`, otherwise they are freed and left dangling
+    #[allow(dead_code)]
+    pub unsafe fn heap_report(&mut self, kept: &[StackObject]) -> HeapReport {
+        self.collect_garbage(kept);
+        self.gc.report()
+    }
+
+    /// forgets loaded modules, globals and stacks and frees every heap object, so that
+    /// next program runs as if on a new VM. Settings such as output and stack limit are kept,
+    /// stacks keep their capacity.
+    ///
+    /// # Safety
+    /// values obtained from previous runs (including entry points) are freed
+    /// and must not be used afterwards
+    #[allow(dead_code)]
+    pub unsafe fn reset(&mut self) {
+        self.reset_stacks();
+        self.loaded_modules.clear();
+        self.snapshots.clear();
+        self.collect_garbage(&[]);
     }
 
     #[inline(always)]
@@ -310,7 +644,23 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
             };
         }
 
-        let jump = match chunk.code[ip] {
+        let mut opcode = chunk.code[ip];
+        if self.coercion == CoercionPolicy::Permissive && VM::is_arithmetic(opcode) {
+            let operands = self.stack.len().saturating_sub(2);
+            for operand in &mut self.stack[operands..] {
+                if let Value::Bool(b) = *operand {
+                    *operand = Value::Int(b as i64);
+                }
+            }
+        }
+        if let Some(method) = self.operator_method(opcode) {
+            //operand is replaced by its bound method which is called with the other operand
+            let first_operand = self.stack.len() - 2;
+            self.stack[first_operand] = method;
+            opcode = Opcode::Call(1);
+        }
+
+        let jump = match opcode {
             Opcode::LoadConst(idx) => {
                 let idx = idx as usize;
                 let value = chunk
@@ -375,18 +725,39 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
             Opcode::Mul => {
                 let second_operand = checked_stack_pop!()?;
                 let first_operand = checked_stack_pop!()?;
-                self.stack
-                    .push(
-                        cast_binary!(&first_operand, *, &second_operand).ok_or_else(|| {
-                            runtime_error!(InterpretErrorKind::TypeError {
-                                message: format!(
-                                    "uncompatible types in Multiply (got {} and {})",
-                                    first_operand.type_string(),
-                                    second_operand.type_string()
-                                )
-                            })
-                        })?,
-                    );
+
+                //string repetition is allowed with count on either side
+                let repetition = match (
+                    first_operand.unwrap_any_str(),
+                    second_operand.unwrap_any_str(),
+                ) {
+                    (Some(s), None) => second_operand.unwrap_int().map(|n| (s, n)),
+                    (None, Some(s)) => first_operand.unwrap_int().map(|n| (s, n)),
+                    _ => None,
+                };
+
+                let value = match repetition {
+                    Some((s, n)) => {
+                        let count = n.max(0) as usize;
+                        //strings cannot be longer than isize::MAX bytes
+                        match s.len().checked_mul(count) {
+                            Some(length) if length <= isize::MAX as usize => {
+                                self.gc.new_string(&s.repeat(count))
+                            }
+                            _ => return Err(runtime_error!(IntegerOverflow)),
+                        }
+                    }
+                    None => cast_binary!(&first_operand, *, &second_operand).ok_or_else(|| {
+                        runtime_error!(InterpretErrorKind::TypeError {
+                            message: format!(
+                                "uncompatible types in Multiply (got {} and {})",
+                                first_operand.type_string(),
+                                second_operand.type_string()
+                            )
+                        })
+                    })?,
+                };
+                self.stack.push(value);
                 InstructionExecution::NextInstruction
             }
 
@@ -558,6 +929,21 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
                 InstructionExecution::NextInstruction
             }
 
+            Opcode::StoreAssociated(idx) => {
+                let value = checked_stack_pop!()?;
+                let pointer = checked_stack_pop!()?;
+                let key = checked_get_name!(idx)?;
+
+                if pointer.set_associated(key, value).is_err() {
+                    return Err(runtime_error!(InterpretErrorKind::AttributeError {
+                        object: pointer,
+                        missed_field: key.to_string()
+                    }));
+                }
+
+                InstructionExecution::NextInstruction
+            }
+
             Opcode::LoadFieldByIndex(idx) => {
                 let pointer = checked_stack_pop!()?;
 
@@ -594,6 +980,52 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
                 InstructionExecution::NextInstruction
             }
 
+            Opcode::CheckLength(expected) => {
+                let value = get_from_top!()?;
+
+                let length = VM::get_elements_count(value).ok_or_else(|| {
+                    runtime_error!(TypeError {
+                        message: format!("cannot destructure {}", value.type_string())
+                    })
+                })?;
+
+                if length != expected as usize {
+                    return Err(runtime_error!(TypeError {
+                        message: format!(
+                            "cannot destructure {} of {} elements into {} names",
+                            value.type_string(),
+                            length,
+                            expected
+                        )
+                    }));
+                }
+
+                InstructionExecution::NextInstruction
+            }
+
+            Opcode::LoadElement(idx) => {
+                let pointer = checked_stack_pop!()?;
+
+                let element = match pointer.unwrap_vector() {
+                    Some(items) => items.get(idx as usize).cloned(),
+                    None => VM::get_property_idx_mut(&pointer, idx as usize).map(|v| v.clone()),
+                };
+
+                match element {
+                    Some(element) => {
+                        self.stack.push(element);
+                    }
+                    None => {
+                        return Err(runtime_error!(InterpretErrorKind::IndexAttributeError {
+                            object: pointer,
+                            missed_idx: idx as usize
+                        }))
+                    }
+                }
+
+                InstructionExecution::NextInstruction
+            }
+
             Opcode::StoreGLobal(idx) => {
                 let key = checked_get_name!(idx)?;
                 let value = checked_stack_pop!()?;
@@ -715,6 +1147,30 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
                 InstructionExecution::LocalJump(new_ip)
             }
 
+            Opcode::PushHandler(delta) => {
+                let new_ip = ip + delta as usize;
+                if new_ip >= chunk.code.len() {
+                    return Err(runtime_error!(JumpBounds));
+                }
+                self.handlers.push(ErrorHandler {
+                    call_depth: self.call_stack.len(),
+                    stack_size: self.stack.len(),
+                    locals_offset: self.locals_offset,
+                    chunk: current_chunk.clone(),
+                    ip: new_ip,
+                });
+                InstructionExecution::NextInstruction
+            }
+
+            Opcode::PopHandler => {
+                self.handlers.pop().ok_or(runtime_error!(StackUnderflow))?;
+                let value = checked_stack_pop!()?;
+                //handler function
+                checked_stack_pop!()?;
+                self.stack.push(value);
+                InstructionExecution::NextInstruction
+            }
+
             Opcode::Pop(n) => {
                 if self.stack.len() < n as usize {
                     return Err(runtime_error!(StackUnderflow));
@@ -730,6 +1186,40 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
                 InstructionExecution::NextInstruction
             }
 
+            Opcode::Negate => {
+                let value = checked_stack_pop!()?;
+                let result = match value {
+                    Value::Int(i) => {
+                        Value::Int(i.checked_neg().ok_or(runtime_error!(IntegerOverflow))?)
+                    }
+                    Value::Float(f) => Value::Float(-f),
+                    other => {
+                        return Err(runtime_error!(TypeError {
+                            message: format!("cannot negate {}", other.type_string())
+                        }))
+                    }
+                };
+                self.stack.push(result);
+                InstructionExecution::NextInstruction
+            }
+
+            Opcode::BitwiseNot => {
+                let value = checked_stack_pop!()?;
+                let result = match value {
+                    Value::Int(i) => Value::Int(!i),
+                    other => {
+                        return Err(runtime_error!(TypeError {
+                            message: format!(
+                                "cannot take bitwise complement of {}",
+                                other.type_string()
+                            )
+                        }))
+                    }
+                };
+                self.stack.push(result);
+                InstructionExecution::NextInstruction
+            }
+
             Opcode::Nop => InstructionExecution::NextInstruction,
             Opcode::Assert => {
                 let value = checked_stack_pop!()?;
@@ -747,6 +1237,80 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
                 }
                 InstructionExecution::NextInstruction
             }
+            Opcode::CastToInt => {
+                let value = checked_stack_pop!()?;
+                let result = match &value {
+                    Value::Int(n) => Some(*n),
+                    //out of range floats are not representable, NaN and infinities included
+                    Value::Float(f) if *f >= i64::MIN as f64 && *f < i64::MAX as f64 => {
+                        Some(f.trunc() as i64)
+                    }
+                    Value::Float(_) => None,
+                    other => other
+                        .unwrap_any_str()
+                        .and_then(|s| s.trim().parse::<i64>().ok()),
+                };
+                self.stack.push(Value::Int(result.ok_or_else(|| {
+                    runtime_error!(TypeError {
+                        message: format!("cannot cast {} `{}` to Int", value.type_string(), value)
+                    })
+                })?));
+                InstructionExecution::NextInstruction
+            }
+
+            Opcode::CastToFloat => {
+                let value = checked_stack_pop!()?;
+                let result = match &value {
+                    Value::Int(n) => Some(*n as f64),
+                    Value::Float(f) => Some(*f),
+                    other => other
+                        .unwrap_any_str()
+                        .and_then(|s| s.trim().parse::<f64>().ok()),
+                };
+                self.stack.push(Value::Float(result.ok_or_else(|| {
+                    runtime_error!(TypeError {
+                        message: format!(
+                            "cannot cast {} `{}` to Float",
+                            value.type_string(),
+                            value
+                        )
+                    })
+                })?));
+                InstructionExecution::NextInstruction
+            }
+
+            Opcode::CastToString => {
+                let value = checked_stack_pop!()?;
+                let result = match &value {
+                    Value::Int(..) | Value::Float(..) | Value::Bool(..) => {
+                        Some(self.gc.new_string(&value.to_string()))
+                    }
+                    other if other.unwrap_any_str().is_some() => Some(value.clone()),
+                    _ => None,
+                };
+                self.stack.push(result.ok_or_else(|| {
+                    runtime_error!(TypeError {
+                        message: format!("cannot cast {} to String", value.type_string())
+                    })
+                })?);
+                InstructionExecution::NextInstruction
+            }
+
+            Opcode::FailAssertion => {
+                let message = checked_stack_pop!()?;
+
+                return Err(match message.unwrap_any_str() {
+                    Some(message) => runtime_error!(AssertionFailureWithMessage {
+                        message: message.to_string()
+                    }),
+                    None => runtime_error!(TypeError {
+                        message: format!(
+                            "assertion message must be String but got {}",
+                            message.type_string()
+                        )
+                    }),
+                });
+            }
             Opcode::Call(arity) => {
                 //check stack
                 let mut arity = arity as usize;
@@ -766,18 +1330,10 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
                     let target = checked_stack_pop!()?;
 
                     //check that all blanks are filled for fully defined call
-                    if !target
-                        .unwrap_partial()
-                        .unwrap()
-                        .get_arity()
-                        .accepts(args.len())
-                    {
+                    let partial_arity = target.unwrap_partial().unwrap().get_arity();
+                    if !partial_arity.accepts(args.len()) {
                         return Err(runtime_error!(TypeError {
-                            message: format!(
-                                "expected {} when calling partial but got {}",
-                                target.unwrap_partial().unwrap().get_arity(),
-                                args.len()
-                            )
+                            message: partial_arity.mismatch_message(args.len())
                         }));
                     }
                     let mut supplied_partial = target.unwrap_partial().unwrap().substitute(args);
@@ -807,7 +1363,7 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
 
                 if !call_arity.accepts(arity) {
                     return Err(runtime_error!(InterpretErrorKind::TypeError {
-                        message: format!("expected {call_arity} but got {arity}")
+                        message: call_arity.mismatch_message(arity)
                     }));
                 }
 
@@ -817,7 +1373,7 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
 
                     let mut args = self.wrap_by_arity(args, call_arity).ok_or_else(|| {
                         runtime_error!(InterpretErrorKind::TypeError {
-                            message: format!("expected {call_arity} but got {arity}")
+                            message: call_arity.mismatch_message(arity)
                         })
                     })?;
                     arity = args.len();
@@ -943,7 +1499,7 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
 
                 if !target_arity.accepts(arity as usize) {
                     return Err(runtime_error!(TypeError {
-                        message: format!("expected {} but got {} args", target_arity, arity)
+                        message: target_arity.mismatch_message(arity)
                     }));
                 }
 
@@ -1088,10 +1644,10 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
 
             Opcode::MakeList(size) => {
                 let size = size as usize;
-                self.check_underflow(size + 1)
+                self.check_underflow(size)
                     .map_err(|_e| runtime_error!(StackUnderflow))?;
 
-                let new_length = self.stack.len().saturating_sub(size);
+                let new_length = self.stack.len() - size;
 
                 let items = self.stack.split_off(new_length);
 
@@ -1099,10 +1655,184 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
 
                 InstructionExecution::NextInstruction
             }
+
+            Opcode::MakeMap(size) => {
+                let size = size as usize;
+                self.check_underflow(size * 2)
+                    .map_err(|_e| runtime_error!(StackUnderflow))?;
+
+                let new_length = self.stack.len() - size * 2;
+                let items = self.stack.split_off(new_length);
+
+                let mut map = VMap::new();
+                for pair in items.chunks(2) {
+                    let (key, value) = (&pair[0], &pair[1]);
+                    if !key.can_hash() {
+                        return Err(runtime_error!(InterpretErrorKind::TypeError {
+                            message: format!("unhashable map key of type {}", key.type_string())
+                        }));
+                    }
+                    map.insert(key.clone(), value.clone());
+                }
+
+                self.stack.push(self.gc.store(map));
+
+                InstructionExecution::NextInstruction
+            }
+
+            Opcode::StoreIndex => {
+                let value = checked_stack_pop!()?;
+                let index = checked_stack_pop!()?;
+                let target = checked_stack_pop!()?;
+
+                if let Some(map) = target.unwrap_map() {
+                    if !index.can_hash() {
+                        return Err(runtime_error!(InterpretErrorKind::TypeError {
+                            message: format!("unhashable map key of type {}", index.type_string())
+                        }));
+                    }
+                    map.insert(index, value);
+                } else if let Some(items) = target.unwrap_vector() {
+                    let idx = Self::element_index(&index, items.len())
+                        .map_err(|kind| runtime_error!(kind))?;
+                    items[idx] = value;
+                } else {
+                    return Err(runtime_error!(InterpretErrorKind::TypeError {
+                        message: format!("cannot assign by index into {}", target.type_string())
+                    }));
+                }
+
+                InstructionExecution::NextInstruction
+            }
+
+            Opcode::LoadIndex => {
+                let index = checked_stack_pop!()?;
+                let target = checked_stack_pop!()?;
+
+                let element = if let Some(map) = target.unwrap_map() {
+                    if !index.can_hash() {
+                        return Err(runtime_error!(InterpretErrorKind::TypeError {
+                            message: format!("unhashable map key of type {}", index.type_string())
+                        }));
+                    }
+                    map.get(&index).cloned().ok_or_else(|| {
+                        runtime_error!(InterpretErrorKind::KeyError { key: index })
+                    })?
+                } else if let Some(items) = target.unwrap_vector() {
+                    let idx = Self::element_index(&index, items.len())
+                        .map_err(|kind| runtime_error!(kind))?;
+                    items[idx].clone()
+                } else if let Some(s) = target.unwrap_any_str() {
+                    let idx = Self::element_index(&index, s.chars().count())
+                        .map_err(|kind| runtime_error!(kind))?;
+                    let character = s.chars().nth(idx).unwrap().to_string();
+                    self.gc.new_string(&character)
+                } else {
+                    return Err(runtime_error!(InterpretErrorKind::TypeError {
+                        message: format!("cannot index into {}", target.type_string())
+                    }));
+                };
+
+                self.stack.push(element);
+
+                InstructionExecution::NextInstruction
+            }
+
+            Opcode::LoadSlice => {
+                let end = checked_stack_pop!()?;
+                let start = checked_stack_pop!()?;
+                let target = checked_stack_pop!()?;
+
+                let slice = if let Some(items) = target.unwrap_vector() {
+                    let (start, end) = Self::slice_bounds(&start, &end, items.len())
+                        .map_err(|kind| runtime_error!(kind))?;
+                    self.gc.store(items[start..end].to_vec())
+                } else if let Some(s) = target.unwrap_any_str() {
+                    let length = s.chars().count();
+                    let (start, end) = Self::slice_bounds(&start, &end, length)
+                        .map_err(|kind| runtime_error!(kind))?;
+                    let part = s.chars().skip(start).take(end - start).collect::<String>();
+                    self.gc.new_string(&part)
+                } else {
+                    return Err(runtime_error!(InterpretErrorKind::TypeError {
+                        message: format!("cannot slice {}", target.type_string())
+                    }));
+                };
+
+                self.stack.push(slice);
+
+                InstructionExecution::NextInstruction
+            }
         };
         Ok(jump)
     }
 
+    /// checks that index points at one of `length` elements
+    fn element_index(
+        index: &Value,
+        length: usize,
+    ) -> std::result::Result<usize, InterpretErrorKind> {
+        let idx = index
+            .unwrap_int()
+            .ok_or_else(|| InterpretErrorKind::TypeError {
+                message: format!("expected Int as index, got {}", index.type_string()),
+            })?;
+        usize::try_from(idx)
+            .ok()
+            .filter(|&idx| idx < length)
+            .ok_or(InterpretErrorKind::IndexOutOfBounds { index: idx, length })
+    }
+
+    /// converts slice bounds into range inside `0..=length`. Missing bounds are Nothing
+    /// and select from start or up to the end, bounds outside of sequence are clamped
+    fn slice_bounds(
+        start: &Value,
+        end: &Value,
+        length: usize,
+    ) -> std::result::Result<(usize, usize), InterpretErrorKind> {
+        let bound = |value: &Value, default: usize| match value {
+            Value::Nothing => Ok(default),
+            other => other
+                .unwrap_int()
+                .map(|idx| idx.clamp(0, length as i64) as usize)
+                .ok_or_else(|| InterpretErrorKind::TypeError {
+                    message: format!("expected Int as slice bound, got {}", other.type_string()),
+                }),
+        };
+        let start = bound(start, 0)?;
+        let end = bound(end, length)?;
+        Ok((start, end.max(start)))
+    }
+
+    fn is_arithmetic(opcode: Opcode) -> bool {
+        matches!(
+            opcode,
+            Opcode::Add | Opcode::Sub | Opcode::Mul | Opcode::Div | Opcode::Mod | Opcode::Power
+        )
+    }
+
+    /// method implementing arithmetic operator for struct instance on the left side,
+    /// like `__add__(self, other)` for `+`
+    fn operator_method(&mut self, opcode: Opcode) -> Option<Value> {
+        let method_name = match opcode {
+            Opcode::Add => "__add__",
+            Opcode::Sub => "__sub__",
+            Opcode::Mul => "__mul__",
+            Opcode::Div => "__div__",
+            Opcode::Mod => "__mod__",
+            Opcode::Power => "__pow__",
+            _ => return None,
+        };
+        let first_operand = self
+            .stack
+            .len()
+            .checked_sub(2)
+            .map(|i| self.stack[i].clone())?;
+        first_operand
+            .unwrap_struct_instance()?
+            .get_bound_method(&first_operand, method_name, self)
+    }
+
     fn check_underflow(&self, needed_args: usize) -> std::result::Result<(), ()> {
         if self.stack.len() < needed_args {
             return Err(());
@@ -1121,10 +1851,20 @@ impl<'gc, 'builtins> VM<'gc, 'builtins> {
         }
     }
 
+    /// number of elements for values supporting destructuring
+    fn get_elements_count(value: &Value) -> Option<usize> {
+        if let Some(items) = value.unwrap_vector() {
+            return Some(items.len());
+        }
+        value
+            .unwrap_struct_instance()
+            .map(|instance| instance.fields.len())
+    }
+
     fn wrap_by_arity(&mut self, mut args: VVec, arity: Arity) -> Option<VVec> {
         if arity.accepts(args.len()) {
             match arity {
-                Arity::Exact(_) => Some(args),
+                Arity::Exact(_) | Arity::Range(..) => Some(args),
                 Arity::AtLeast(expected) => {
                     let vararg_part = args.split_off(expected);
 
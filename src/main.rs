@@ -1,8 +1,9 @@
+use crate::compile::checks::LintConfig;
 use crate::data::gc::GC;
 
 use crate::execution::builtins::builtin_factory;
 use crate::execution::chunk::Chunk;
-use crate::execution::module::{compile_file, compile_program, Module};
+use crate::execution::module::{compile_file, compile_program, format_program, Module};
 use crate::execution::vm::VM;
 use crate::parsing::ast::Expr;
 use execution::chunk::Opcode;
@@ -30,16 +31,29 @@ mod test;
 
 fn main() {
     let args = env::args().collect::<Vec<_>>();
-    if args.len() != 2 {
-        run_repl();
-        return;
-    }
-    let filename = args.get(1).unwrap();
+    let mut lints = LintConfig::default();
+    let filename = match args.as_slice() {
+        [_, flag, filename] if flag == "--fmt" => {
+            let source = std::fs::read_to_string(filename).unwrap();
+            print!("{}", format_program(source).unwrap());
+            return;
+        }
+        [_, flag, filename] if flag == "--lint" => {
+            lints = LintConfig::all();
+            filename
+        }
+        [_, filename] => filename,
+        _ => {
+            run_repl();
+            return;
+        }
+    };
 
     let mut gc = unsafe { GC::default_gc() };
     let builtins = builtin_factory();
 
     let mut vm = VM::new(&mut gc, &builtins);
+    vm.set_lint_config(lints);
 
     let (source, pointer) = compile_file(Path::new(filename), &mut vm).unwrap();
 
@@ -1,4 +1,5 @@
 use crate::parsing::lexer::Token;
+use std::ops::Range;
 
 #[derive(Clone, Debug)]
 pub struct EnumVariant {
@@ -6,13 +7,67 @@ pub struct EnumVariant {
     pub fields: Vec<Token>,
 }
 
+/// left side of destructuring declaration, e.g. `(a, [b, c])`
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    Name(Token),
+    /// `(a, b)` with token of opening paren
+    Tuple(Token, Vec<Pattern>),
+    /// `[a, b]` with token of opening bracket
+    List(Token, Vec<Pattern>),
+}
+
+impl Pattern {
+    pub fn token(&self) -> &Token {
+        match self {
+            Pattern::Name(t) | Pattern::Tuple(t, _) | Pattern::List(t, _) => t,
+        }
+    }
+
+    /// names bound by pattern in order of appearance
+    pub fn names(&self) -> Vec<&Token> {
+        self.bindings().into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// names bound by pattern together with path to corresponding value.
+    /// Every path item is (expected element count, element index)
+    pub fn bindings(&self) -> Vec<(&Token, Vec<(usize, usize)>)> {
+        let mut result = vec![];
+        self.collect_bindings(&mut vec![], &mut result);
+        result
+    }
+
+    fn collect_bindings<'a>(
+        &'a self,
+        path: &mut Vec<(usize, usize)>,
+        result: &mut Vec<(&'a Token, Vec<(usize, usize)>)>,
+    ) {
+        match self {
+            Pattern::Name(name) => result.push((name, path.clone())),
+            Pattern::Tuple(_, items) | Pattern::List(_, items) => {
+                for (i, item) in items.iter().enumerate() {
+                    path.push((items.len(), i));
+                    item.collect_bindings(path, result);
+                    path.pop();
+                }
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Stmt {
     VarDeclaration(Token, Option<Expr>),
+    DestructuringDeclaration(Pattern, Expr),
+    ConstDeclaration(Token, Expr),
     Assignment(Token, Expr),
     PropertyAssignment(Expr, Expr),
+    /// `target[index] = value` with token of opening bracket
+    IndexAssignment(Expr, Token, Expr, Expr),
     Expression(Expr),
-    Assert(Token, Expr),
+    /// `assert cond` or `assert cond, message` with byte range of `cond` in source.
+    /// Range ends where the next token starts, so it may include trailing comment
+    Assert(Token, Expr, Range<usize>, Option<Expr>),
     Pass(Token),
     FunctionDeclaration {
         name: Token,
@@ -42,6 +97,12 @@ pub enum Stmt {
     },
 }
 
+/// function of impl block is a method if its first argument is `self`,
+/// otherwise it is associated function called on type itself like `Point.origin()`
+pub fn takes_self(args: &[Token]) -> bool {
+    args.first().and_then(Token::get_string) == Some("self")
+}
+
 #[derive(Clone, Debug)]
 pub enum Expr {
     Number(Token),
@@ -59,6 +120,20 @@ pub enum Expr {
     AnonFunction(Vec<Token>, Option<Token>, Token, Box<Expr>),
     PropertyAccess(Box<Expr>, Token),
     PropertyTest(Box<Expr>, Token),
+    /// `{k: v, ...}` with token of opening brace
+    MapLiteral(Token, Vec<(Expr, Expr)>),
+    /// `[a, b, ...]` with token of opening bracket
+    ListLiteral(Token, Vec<Expr>),
+    /// `(a, b, ...)` or `(a,)` with token of opening paren, built as list at runtime
+    TupleLiteral(Token, Vec<Expr>),
+    /// `target[index]` with token of opening bracket
+    Index(Box<Expr>, Token, Box<Expr>),
+    /// `target[start:end]` with token of opening bracket, both bounds are optional
+    Slice(Box<Expr>, Token, Option<Box<Expr>>, Option<Box<Expr>>),
+    /// `value as Target` with `as` token and target type name
+    Cast(Box<Expr>, Token, Token),
+    /// `try body catch (e) handler` with `try` token, handler is kept as function of `e`
+    TryCatch(Token, Box<Expr>, Box<Expr>),
 }
 
 pub type Program = Vec<Stmt>;
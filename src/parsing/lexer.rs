@@ -3,12 +3,15 @@ use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
 use std::iter::Peekable;
 use std::mem;
+use std::ops::Range;
 use std::str::CharIndices;
 
 use ordered_float::NotNan;
 
+/// position in source: line and column (both starting from 1) and byte offset of the
+/// first character, so `&source[index.2..]` starts with the token
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
-pub struct Index(pub usize, pub usize);
+pub struct Index(pub usize, pub usize, pub usize);
 
 impl Display for Index {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -30,6 +33,10 @@ pub enum TokenKind {
 
     LParen,
     RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
 
     Plus,
     Minus,
@@ -45,6 +52,7 @@ pub enum TokenKind {
     Comma,
     Dot,
     QuestionMark,
+    Tilde,
 
     CompareEquals,
     CompareNotEquals,
@@ -70,6 +78,7 @@ pub enum TokenKind {
 
     Assert,
     Var,
+    Const,
     If,
     Elif,
     Else,
@@ -80,6 +89,8 @@ pub enum TokenKind {
     Impl,
     Import,
     As,
+    Try,
+    Catch,
 }
 
 impl Display for TokenKind {
@@ -94,6 +105,10 @@ impl Display for TokenKind {
                 TokenKind::Slash => "/".to_string(),
                 TokenKind::LParen => "(".to_string(),
                 TokenKind::RParen => ")".to_string(),
+                TokenKind::LBracket => "[".to_string(),
+                TokenKind::RBracket => "]".to_string(),
+                TokenKind::LBrace => "{".to_string(),
+                TokenKind::RBrace => "}".to_string(),
                 TokenKind::Equals => "=".to_string(),
                 TokenKind::CompareEquals => "==".to_string(),
                 TokenKind::CompareNotEquals => "!=".to_string(),
@@ -103,6 +118,7 @@ impl Display for TokenKind {
                 TokenKind::CompareLessEqual => "<=".to_string(),
                 TokenKind::Dot => ".".to_string(),
                 TokenKind::QuestionMark => "?".to_string(),
+                TokenKind::Tilde => "~".to_string(),
                 TokenKind::Power => "**".to_string(),
                 TokenKind::Or => "or".to_string(),
                 TokenKind::And => "and".to_string(),
@@ -119,6 +135,7 @@ impl Token {
             TokenKind::Name(n) => Some(n),
             TokenKind::ConstString(s) => Some(s),
             TokenKind::Arrow => Some("anon function"),
+            TokenKind::Catch => Some("catch handler"),
             _ => None,
         }
     }
@@ -138,11 +155,270 @@ impl Token {
     }
 }
 
+/// digits of `i64::MIN` without sign, the only literal that is valid only after minus
+const MIN_INT_MAGNITUDE: &str = "9223372036854775808";
+
 pub fn tokenize(input: &str) -> Result<Vec<Token>, String> {
     let mut lexer = Lexer::new(input);
     lexer.tokenize()
 }
 
+/// `source` up to comment that starts outside of string literals and backtick names
+pub fn strip_comment(source: &str) -> &str {
+    fn skip_literal(chars: &mut impl Iterator<Item = (usize, char)>, closing: char, escapes: bool) {
+        while let Some((_, character)) = chars.next() {
+            match character {
+                '\\' if escapes => {
+                    chars.next();
+                }
+                c if c == closing => return,
+                _ => {}
+            }
+        }
+    }
+
+    let mut chars = source.char_indices().peekable();
+    while let Some((idx, character)) = chars.next() {
+        match character {
+            '#' => return &source[..idx],
+            '`' => skip_literal(&mut chars, '`', false),
+            '"' => skip_literal(&mut chars, '"', true),
+            //names are skipped whole, so this is r"..." and not a name ending in r
+            'r' if matches!(chars.peek(), Some((_, '"'))) => {
+                chars.next();
+                skip_literal(&mut chars, '"', false)
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                while chars
+                    .next_if(|(_, c)| c.is_alphanumeric() || *c == '_')
+                    .is_some()
+                {}
+            }
+            _ => {}
+        }
+    }
+    source
+}
+
+/// whether name can be written without backticks, i.e. it is an identifier and not a keyword
+pub fn is_plain_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && matches!(Lexer::new(name).keyword_or_name(name), TokenKind::Name(_))
+}
+
+/// lexer state at the start of a line, enough to continue tokenizing from there
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct LineState {
+    offset: usize,
+    line_number: usize,
+    indentation: Vec<usize>,
+    brackets: Vec<Token>,
+    /// number of tokens produced before the line
+    token_count: usize,
+    /// last token produced before the line. It may still be moved or replaced by the lexer,
+    /// while all tokens before it are final
+    last_token: Token,
+}
+
+/// moves positions of tokens that were after an edit to where they are after it
+#[derive(Clone, Copy)]
+struct Shift {
+    /// offset in old text where unchanged text after the edit starts
+    old_end: usize,
+    lines: isize,
+    bytes: isize,
+}
+
+impl Shift {
+    fn index(&self, index: Index) -> Index {
+        if index.2 < self.old_end {
+            return index;
+        }
+        Index(
+            (index.0 as isize + self.lines) as usize,
+            index.1,
+            (index.2 as isize + self.bytes) as usize,
+        )
+    }
+
+    fn token(&self, token: &Token) -> Token {
+        Token {
+            position: self.index(token.position),
+            kind: token.kind.clone(),
+        }
+    }
+
+    fn state(&self, state: &LineState, tokens: isize) -> LineState {
+        LineState {
+            offset: (state.offset as isize + self.bytes) as usize,
+            line_number: (state.line_number as isize + self.lines) as usize,
+            indentation: state.indentation.clone(),
+            brackets: state.brackets.iter().map(|t| self.token(t)).collect(),
+            token_count: (state.token_count as isize + tokens) as usize,
+            last_token: self.token(&state.last_token),
+        }
+    }
+}
+
+/// tokens of source that is edited in place, e.g. by an editor. After an edit only lines from
+/// the changed one up to the first line where lexer is back in the state it had before the edit
+/// are tokenized again, tokens after that are reused with shifted positions.
+/// Tokens are always the same as [`tokenize`] would produce for whole source
+#[allow(dead_code)]
+pub struct TokenBuffer {
+    source: String,
+    tokens: Vec<Token>,
+    /// states at starts of tokenized lines, ordered by offset
+    lines: Vec<LineState>,
+    error: Option<String>,
+}
+
+#[allow(dead_code)]
+impl TokenBuffer {
+    pub fn new(source: &str) -> TokenBuffer {
+        let mut lexer = Lexer::new(source);
+        let mut tokens = vec![Token {
+            position: lexer.compute_index(),
+            kind: TokenKind::BeginBlock,
+        }];
+        lexer.indentation.push(0);
+
+        let mut buffer = TokenBuffer {
+            source: source.to_string(),
+            lines: vec![lexer.line_state(&tokens)],
+            tokens: vec![],
+            error: None,
+        };
+        let result =
+            TokenBuffer::read_rest(
+                &mut lexer,
+                &mut tokens,
+                &mut buffer.lines,
+                |_, _| None::<()>,
+            );
+        buffer.store(result.map(|_| ()), tokens, 1);
+        buffer
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// tokens of current source or error that tokenizing it produced
+    pub fn tokens(&self) -> Result<&[Token], &str> {
+        match &self.error {
+            Some(error) => Err(error),
+            None => Ok(&self.tokens),
+        }
+    }
+
+    /// replaces bytes of source in `range` with `replacement` and updates tokens
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) {
+        let removed_lines = self.source[range.clone()].matches('\n').count();
+        self.source.replace_range(range.clone(), replacement);
+        let shift = Shift {
+            old_end: range.end,
+            lines: replacement.matches('\n').count() as isize - removed_lines as isize,
+            bytes: replacement.len() as isize - range.len() as isize,
+        };
+
+        //text before the edit did not change, so does the state at start of its line
+        let resume_at = self
+            .lines
+            .iter()
+            .rposition(|line| line.offset <= range.start)
+            .unwrap();
+        let old_lines = self.lines.split_off(resume_at + 1);
+        let start = self.lines[resume_at].clone();
+        let old_tokens = mem::take(&mut self.tokens);
+        let mut tokens = old_tokens[..start.token_count - 1].to_vec();
+        tokens.push(start.last_token.clone());
+
+        let source = mem::take(&mut self.source);
+        let mut lexer = Lexer::resume(&source, &start);
+        let result =
+            TokenBuffer::read_rest(&mut lexer, &mut tokens, &mut self.lines, |state, tokens| {
+                //lines that were before the edit can not be reused
+                let reused_from = old_lines.iter().position(|old| {
+                    old.offset >= range.end && shift.state(old, 0).offset == state.offset
+                })?;
+                let old = &old_lines[reused_from];
+                let token_delta = state.token_count as isize - old.token_count as isize;
+                //next line may look at two last tokens
+                let before_last =
+                    |tokens: &[Token], count: usize| tokens.get(count.wrapping_sub(2)).cloned();
+                let old_before_last =
+                    before_last(&old_tokens, old.token_count).map(|t| shift.token(&t));
+
+                (shift.state(old, token_delta) == *state
+                    && old_before_last == before_last(tokens, state.token_count))
+                .then_some((reused_from, token_delta))
+            });
+        self.source = source;
+
+        let result = result.map(|reused| {
+            if let Some((reused_from, token_delta)) = reused {
+                //last token before the line is taken in its final form
+                tokens.pop();
+                let old_token_count = old_lines[reused_from].token_count;
+                tokens.extend(
+                    old_tokens[old_token_count - 1..]
+                        .iter()
+                        .map(|t| shift.token(t)),
+                );
+                let reused_lines = old_lines[reused_from..]
+                    .iter()
+                    .map(|old| shift.state(old, token_delta));
+                self.lines.extend(reused_lines);
+            }
+        });
+        self.store(result, tokens, resume_at + 1);
+    }
+
+    /// tokenizes from where lexer stopped, recording state at every line start.
+    /// Stops with value returned by `reuse` once it returns something for a line
+    fn read_rest<T>(
+        lexer: &mut Lexer,
+        tokens: &mut Vec<Token>,
+        lines: &mut Vec<LineState>,
+        mut reuse: impl FnMut(&LineState, &[Token]) -> Option<T>,
+    ) -> Result<Option<T>, String> {
+        let mut reused = None;
+        let finished = lexer.read_lines(tokens, &mut |lexer, tokens| {
+            let state = lexer.line_state(tokens);
+            reused = reuse(&state, tokens);
+            if reused.is_none() {
+                lines.push(state);
+            }
+            reused.is_some()
+        })?;
+        if finished {
+            lexer.finish(tokens)?;
+        }
+        Ok(reused)
+    }
+
+    /// keeps tokens produced by full tokenizing of the rest of source. On error only lines before
+    /// the edit are kept, so that next edit does not reuse anything that was not tokenized
+    fn store(&mut self, result: Result<(), String>, tokens: Vec<Token>, valid_lines: usize) {
+        match result {
+            Ok(()) => {
+                self.tokens = tokens;
+                self.error = None;
+            }
+            Err(error) => {
+                self.lines.truncate(valid_lines);
+                let kept_tokens = self.lines.last().unwrap().token_count;
+                self.tokens = tokens;
+                self.tokens.truncate(kept_tokens);
+                self.error = Some(error);
+            }
+        }
+    }
+}
+
 struct Lexer<'input> {
     input_string: &'input str,
     input_iterator: Peekable<CharIndices<'input>>,
@@ -160,6 +436,7 @@ impl<'input> Lexer<'input> {
         let keywords = vec![
             ("assert", Assert),
             ("var", Var),
+            ("const", Const),
             ("if", If),
             ("elif", Elif),
             ("else", Else),
@@ -177,6 +454,8 @@ impl<'input> Lexer<'input> {
             ("false", False),
             ("import", Import),
             ("as", As),
+            ("try", Try),
+            ("catch", Catch),
         ]
         .into_iter()
         .map(|(k, v)| (k.to_string(), v))
@@ -190,6 +469,7 @@ impl<'input> Lexer<'input> {
             (',', Comma),
             ('.', Dot),
             ('?', QuestionMark),
+            ('~', Tilde),
             (':', Colon),
             (';', Semicolon),
         ]
@@ -210,7 +490,11 @@ impl<'input> Lexer<'input> {
 
     fn compute_index(&mut self) -> Index {
         let current_pos = self.compute_input_shift();
-        Index(self.line_number + 1, current_pos - self.line_start + 1)
+        Index(
+            self.line_number + 1,
+            current_pos - self.line_start + 1,
+            current_pos,
+        )
     }
 
     fn compute_input_shift(&mut self) -> usize {
@@ -232,14 +516,54 @@ impl<'input> Lexer<'input> {
     }
 
     pub fn tokenize(&mut self) -> Result<Vec<Token>, String> {
-        use TokenKind::*;
         let mut result = vec![Token {
             position: self.compute_index(),
-            kind: BeginBlock,
+            kind: TokenKind::BeginBlock,
         }];
 
         self.indentation.push(0);
 
+        self.read_lines(&mut result, &mut |_, _| false)?;
+        self.finish(&mut result)?;
+        Ok(result)
+    }
+
+    /// state to continue tokenizing from, only meaningful at the start of a line
+    fn line_state(&self, tokens: &[Token]) -> LineState {
+        LineState {
+            offset: self.line_start,
+            line_number: self.line_number,
+            indentation: self.indentation.clone(),
+            brackets: self.brackets.clone(),
+            token_count: tokens.len(),
+            last_token: tokens.last().unwrap().clone(),
+        }
+    }
+
+    /// creates lexer that continues from the start of a line, as if everything before it
+    /// was already tokenized
+    fn resume(input_string: &'input str, state: &LineState) -> Lexer<'input> {
+        let mut lexer = Lexer::new(input_string);
+        while matches!(lexer.input_iterator.peek(), Some(&(idx, _)) if idx < state.offset) {
+            lexer.input_iterator.next();
+        }
+        lexer.line_number = state.line_number;
+        lexer.line_start = state.offset;
+        lexer.indentation = state.indentation.clone();
+        lexer.brackets = state.brackets.clone();
+        lexer
+    }
+
+    /// tokenizes input line by line, starting at the beginning of a line. `at_line_start` is
+    /// called after every newline and stops tokenizing by returning true.
+    /// Returns whether input was read to the end
+    fn read_lines(
+        &mut self,
+        result: &mut Vec<Token>,
+        at_line_start: &mut dyn FnMut(&Lexer, &[Token]) -> bool,
+    ) -> Result<bool, String> {
+        use TokenKind::*;
+
         let mut is_reading_indentation = true;
 
         while let Some((_, character)) = self.input_iterator.peek() {
@@ -303,6 +627,9 @@ impl<'input> Lexer<'input> {
                         .unwrap_or(&(self.input_string.len(), '\0'))
                         .0;
                     is_reading_indentation = true;
+                    if at_line_start(self, result) {
+                        return Ok(false);
+                    }
                 }
 
                 '#' => {
@@ -334,21 +661,54 @@ impl<'input> Lexer<'input> {
 
                     self.read_while(&|c| c.is_numeric());
 
-                    if let Some((_, '.')) = self.input_iterator.peek() {
+                    let has_fraction = matches!(self.input_iterator.peek(), Some((_, '.')));
+                    if has_fraction {
                         self.input_iterator.next();
                         self.read_while(&|c| c.is_numeric());
-                        let end_idx = self.compute_input_shift();
-                        let string = self.input_string[start_idx..end_idx].to_string();
+                    }
+                    let end_idx = self.compute_input_shift();
+
+                    //optional suffix: 1i is Int, 1f is Float
+                    self.read_while(&|c| c.is_alphanumeric() || c == '_');
+                    let suffix = &self.input_string[end_idx..self.compute_input_shift()];
+                    let string = &self.input_string[start_idx..end_idx];
+                    let is_float = match suffix {
+                        "" => has_fraction,
+                        "f" => true,
+                        "i" if !has_fraction => false,
+                        _ => {
+                            return Err(format!(
+                                "invalid suffix {} on numeric literal {} [{}]",
+                                suffix, string, token_index
+                            ))
+                        }
+                    };
+
+                    if is_float {
                         let number: f64 = string.parse().unwrap();
                         result.push(token!(
                             token_index,
                             FloatNumber(NotNan::new(number).unwrap())
                         ));
                     } else {
-                        let end_idx = self.compute_input_shift();
-                        let string = self.input_string[start_idx..end_idx].to_string();
-                        let number: i64 = string.parse().unwrap();
-                        result.push(token!(token_index, Number(number)));
+                        match string.parse::<i64>() {
+                            Ok(number) => result.push(token!(token_index, Number(number))),
+                            //magnitude of i64::MIN only fits when negated,
+                            //so minus sign is folded into literal here
+                            Err(_)
+                                if string == MIN_INT_MAGNITUDE
+                                    && Lexer::ends_with_prefix_minus(result) =>
+                            {
+                                let minus = result.pop().unwrap();
+                                result.push(token!(minus.position, Number(i64::MIN)));
+                            }
+                            Err(_) => {
+                                return Err(format!(
+                                    "integer literal {} is too large [{}]",
+                                    string, token_index
+                                ))
+                            }
+                        }
                     }
                 }
 
@@ -360,6 +720,15 @@ impl<'input> Lexer<'input> {
 
                     let end_idx = self.compute_input_shift();
                     let name = &self.input_string[start_idx..end_idx];
+
+                    if name == "r" && matches!(self.input_iterator.peek(), Some((_, '"'))) {
+                        //raw string r"..."
+                        self.input_iterator.next(); //skip opening "
+                        let s = self.read_string_literal(token_index, true)?;
+                        result.push(token!(token_index, TokenKind::ConstString(s)));
+                        continue;
+                    }
+
                     //keywords
                     result.push(token!(token_index, self.keyword_or_name(name)))
                 }
@@ -379,32 +748,34 @@ impl<'input> Lexer<'input> {
                 '"' => {
                     let token_index = self.compute_index();
                     self.input_iterator.next(); //skip opening "
-                    let start_idx = self.compute_input_shift();
-                    self.read_while(&|c| c != '"');
-                    if self.input_iterator.peek().is_none() {
-                        return Err(format!("unterminated string at [{}]", token_index));
-                    }
-                    let end_idx = self.compute_input_shift();
-                    self.input_iterator.next(); //skip closing "
-                    let s = self.input_string[start_idx..end_idx].to_string();
+                    let s = self.read_string_literal(token_index, false)?;
                     result.push(token!(token_index, TokenKind::ConstString(s)));
                 }
 
-                '(' => {
-                    let token = token!(LParen);
+                '(' | '[' | '{' => {
+                    let token = token!(match character {
+                        '(' => LParen,
+                        '[' => LBracket,
+                        _ => LBrace,
+                    });
                     result.push(token.clone());
                     self.brackets.push(token);
                     self.input_iterator.next();
                 }
-                ')' => {
-                    let token = token!(RParen);
-                    if let Some(TokenKind::LParen) = self.brackets.last().map(|t| t.kind.clone()) {
+                ')' | ']' | '}' => {
+                    let (token, opening) = match character {
+                        ')' => (token!(RParen), LParen),
+                        ']' => (token!(RBracket), LBracket),
+                        _ => (token!(RBrace), LBrace),
+                    };
+                    if Some(&opening) == self.brackets.last().map(|t| &t.kind) {
                         self.brackets.pop();
                         result.push(token);
                         self.input_iterator.next();
                     } else {
                         return Err(format!(
-                            "encountered unbalanced `)` at [{}] (matched with {})",
+                            "encountered unbalanced `{}` at [{}] (matched with {})",
+                            character,
                             token.position,
                             match self.brackets.last() {
                                 None => {
@@ -500,6 +871,11 @@ impl<'input> Lexer<'input> {
             }
         }
 
+        Ok(true)
+    }
+
+    /// reports unclosed brackets and closes blocks that are still open at the end of input
+    fn finish(&mut self, result: &mut Vec<Token>) -> Result<(), String> {
         if !self.brackets.is_empty() {
             let msg = self
                 .brackets
@@ -513,12 +889,12 @@ impl<'input> Lexer<'input> {
         while !self.indentation.is_empty() {
             result.push(Token {
                 position: self.compute_index(),
-                kind: EndBlock,
+                kind: TokenKind::EndBlock,
             });
             self.indentation.pop();
         }
 
-        Ok(result)
+        Ok(())
     }
 
     fn read_while<F: (Fn(char) -> bool)>(&mut self, predicate: &F) {
@@ -538,6 +914,99 @@ impl<'input> Lexer<'input> {
         }
     }
 
+    /// whether last token is minus sign that starts an operand rather than subtracts
+    fn ends_with_prefix_minus(tokens: &[Token]) -> bool {
+        use TokenKind::*;
+        match tokens {
+            [.., before, last] if last.kind == Minus => !matches!(
+                before.kind,
+                Number(..)
+                    | FloatNumber(..)
+                    | Name(..)
+                    | ConstString(..)
+                    | True
+                    | False
+                    | Blank
+                    | RParen
+                    | RBracket
+                    | RBrace
+            ),
+            _ => false,
+        }
+    }
+
+    /// consumes next character that belongs to a token
+    fn next_in_token(&mut self) -> Option<char> {
+        let (idx, character) = self.input_iterator.next()?;
+        if character == '\n' {
+            self.line_number += 1;
+            self.line_start = idx + 1;
+            eprintln!("warning: encountered newline inside token");
+        }
+        Some(character)
+    }
+
+    /// reads string contents after opening quote up to and including closing quote.
+    /// Raw strings end at the first `"` and keep backslashes, other strings
+    /// decode `\n`, `\t`, `\r`, `\0`, `\\` and `\"`
+    fn read_string_literal(&mut self, start: Index, raw: bool) -> Result<String, String> {
+        let mut content = String::new();
+        loop {
+            let position = self.compute_index();
+            match self.next_in_token() {
+                None => return Err(format!("unterminated string at [{}]", start)),
+                Some('"') => return Ok(content),
+                Some('\\') if !raw => match self.next_in_token() {
+                    Some('n') => content.push('\n'),
+                    Some('t') => content.push('\t'),
+                    Some('r') => content.push('\r'),
+                    Some('0') => content.push('\0'),
+                    Some('\\') => content.push('\\'),
+                    Some('"') => content.push('"'),
+                    Some('u') => content.push(self.read_unicode_escape(position)?),
+                    Some(other) => {
+                        return Err(format!(
+                            "unknown escape sequence \\{} in string at [{}]",
+                            other, position
+                        ))
+                    }
+                    None => return Err(format!("unterminated string at [{}]", start)),
+                },
+                Some(character) => content.push(character),
+            }
+        }
+    }
+
+    /// reads `{XXXX}` part of `\u{XXXX}` escape starting at `position`, up to 6 hex digits
+    fn read_unicode_escape(&mut self, position: Index) -> Result<char, String> {
+        let malformed = || {
+            format!(
+                "malformed unicode escape in string at [{}], expected \\u{{XXXX}}",
+                position
+            )
+        };
+
+        if self.next_in_token() != Some('{') {
+            return Err(malformed());
+        }
+        let mut digits = String::new();
+        loop {
+            match self.next_in_token() {
+                Some('}') => break,
+                Some(digit) if digit.is_ascii_hexdigit() && digits.len() < 6 => digits.push(digit),
+                _ => return Err(malformed()),
+            }
+        }
+
+        let code = u32::from_str_radix(&digits, 16).map_err(|_| malformed())?;
+        char::from_u32(code).ok_or_else(|| {
+            format!(
+                "invalid unicode code point {:X} in string at [{}]",
+                code, position
+            )
+        })
+    }
+
     fn read_identation(&mut self) -> Result<Vec<Token>, String> {
         use TokenKind::{BeginBlock, EndBlock};
         let mut result = vec![];
@@ -606,3 +1075,206 @@ impl<'input> Lexer<'input> {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{strip_comment, tokenize, Index, TokenBuffer, TokenKind};
+
+    #[test]
+    fn token_offset_should_slice_source_at_lexeme() {
+        let source = "def f(x) =\n    var s = \"str\"\n    x ** 2.5 + `odd name`\n";
+        let tokens = tokenize(source).unwrap();
+
+        let lexemes = tokens
+            .iter()
+            .filter_map(|token| {
+                let lexeme = match &token.kind {
+                    TokenKind::Name(name) => name.clone(),
+                    TokenKind::ConstString(s) => format!("\"{s}\""),
+                    TokenKind::Power => "**".to_string(),
+                    TokenKind::FloatNumber(_) => "2.5".to_string(),
+                    _ => return None,
+                };
+                Some((token.position, lexeme))
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(lexemes.len(), 8);
+        for (position, lexeme) in lexemes {
+            assert_eq!(&source[position.2..position.2 + lexeme.len()], lexeme);
+        }
+    }
+
+    fn single_string(source: &str) -> Result<String, String> {
+        let tokens = tokenize(source)?;
+        match &tokens[1].kind {
+            TokenKind::ConstString(s) => Ok(s.clone()),
+            other => panic!("expected string but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn string_escapes_should_be_decoded() {
+        assert_eq!(
+            single_string(r#""a\nb\t\"q\" \\ \0""#).unwrap(),
+            "a\nb\t\"q\" \\ \0"
+        );
+        assert_eq!(
+            single_string(r#""bad \q""#).unwrap_err(),
+            "unknown escape sequence \\q in string at [1:6]"
+        );
+    }
+
+    #[test]
+    fn unicode_escapes_should_be_decoded() {
+        assert_eq!(single_string(r#""\u{e9}t\u{E9}""#).unwrap(), "été");
+        assert_eq!(single_string(r#""\u{1F600}!""#).unwrap(), "😀!");
+        assert_eq!(
+            single_string(r#""a \u{D800}""#).unwrap_err(),
+            "invalid unicode code point D800 in string at [1:4]"
+        );
+        for malformed in [r#""\u0041""#, r#""\u{}""#, r#""\u{41""#, r#""\u{1234567}""#] {
+            assert_eq!(
+                single_string(malformed).unwrap_err(),
+                "malformed unicode escape in string at [1:2], expected \\u{XXXX}"
+            );
+        }
+    }
+
+    #[test]
+    fn raw_string_should_keep_backslashes() {
+        assert_eq!(single_string(r#"r"C:\path\n""#).unwrap(), "C:\\path\\n");
+        assert_eq!(single_string(r#"r"\""#).unwrap(), "\\");
+        assert!(single_string(r#"r"unterminated"#).is_err());
+    }
+
+    #[test]
+    fn name_r_should_not_start_raw_string() {
+        let tokens = tokenize("r + rr").unwrap();
+        assert_eq!(tokens[1].kind, TokenKind::Name("r".to_string()));
+    }
+
+    #[test]
+    fn smallest_int_should_be_lexed_only_with_minus() {
+        let tokens = tokenize("f(-9223372036854775808)").unwrap();
+        assert_eq!(tokens[3].kind, TokenKind::Number(i64::MIN));
+        assert_eq!(tokens[3].position, Index(1, 3, 2));
+
+        assert_eq!(
+            tokenize("9223372036854775808").unwrap_err(),
+            "integer literal 9223372036854775808 is too large [1:1]"
+        );
+        //binary minus is not folded
+        assert!(tokenize("x -9223372036854775808").is_err());
+        assert!(tokenize("-9223372036854775809").is_err());
+    }
+
+    #[test]
+    fn numeric_suffixes_should_select_literal_type() {
+        let tokens = tokenize("1f + 1i + 2.5f").unwrap();
+        assert_eq!(
+            tokens[1].kind,
+            TokenKind::FloatNumber(1.0.try_into().unwrap())
+        );
+        assert_eq!(tokens[3].kind, TokenKind::Number(1));
+        assert_eq!(
+            tokens[5].kind,
+            TokenKind::FloatNumber(2.5.try_into().unwrap())
+        );
+        assert_eq!(tokens[5].position, Index(1, 11, 10));
+
+        assert_eq!(
+            tokenize("1x").unwrap_err(),
+            "invalid suffix x on numeric literal 1 [1:1]"
+        );
+        assert!(tokenize("2.5i").is_err());
+        assert!(tokenize("1if").is_err());
+    }
+
+    #[test]
+    fn token_offset_should_agree_with_line_and_column() {
+        let source = "var a = 1\nvar bb = 2\n";
+        let tokens = tokenize(source).unwrap();
+        let bb = tokens
+            .iter()
+            .find(|t| t.kind == TokenKind::Name("bb".to_string()))
+            .unwrap();
+
+        assert_eq!(bb.position, Index(2, 5, 14));
+    }
+
+    #[test]
+    fn comment_should_be_stripped_outside_of_literals() {
+        assert_eq!(strip_comment("x == 1 # note"), "x == 1 ");
+        assert_eq!(strip_comment("s == \"#\\\"#\" #"), "s == \"#\\\"#\" ");
+        assert_eq!(
+            strip_comment("r\"\\\" == `a#b` # note"),
+            "r\"\\\" == `a#b` "
+        );
+        assert_eq!(strip_comment("bar\"#\""), "bar\"#\"");
+        assert_eq!(strip_comment("x\n"), "x\n");
+    }
+
+    fn assert_matches_full_tokenize(buffer: &TokenBuffer) {
+        let expected = tokenize(buffer.source());
+        assert_eq!(
+            buffer.tokens().map(<[_]>::to_vec).map_err(str::to_string),
+            expected,
+            "source:\n{}",
+            buffer.source()
+        );
+    }
+
+    #[test]
+    fn edited_buffer_should_match_full_tokenize() {
+        let source = "def f(x) =\n    var y = x\n    y * 2\n\nassert f(1) == 2\n";
+        let mut buffer = TokenBuffer::new(source);
+        assert_matches_full_tokenize(&buffer);
+
+        //rename inside a line
+        buffer.edit(19..20, "value");
+        assert_matches_full_tokenize(&buffer);
+        //new lines shift positions of everything below
+        buffer.edit(0..0, "var a = (1,\n    2)\n");
+        assert_matches_full_tokenize(&buffer);
+        //unclosed bracket fails until it is closed again
+        buffer.edit(0..0, "(");
+        assert!(buffer.tokens().is_err());
+        buffer.edit(0..1, "");
+        assert_matches_full_tokenize(&buffer);
+    }
+
+    #[test]
+    fn random_edits_should_match_full_tokenize() {
+        let snippets = [
+            "x",
+            " ",
+            "\n",
+            "    ",
+            "(",
+            ")",
+            "[",
+            "\"",
+            "#",
+            "-",
+            "1",
+            "==",
+            "\n    var y = 2\n",
+        ];
+        let mut buffer = TokenBuffer::new(include_str!("../../examples/consts.txt"));
+        let mut seed: u64 = 42;
+        let mut next = |bound: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % bound
+        };
+
+        for _ in 0..500 {
+            let start = next(buffer.source().len() + 1);
+            let end = (start + next(4)).min(buffer.source().len());
+            buffer.edit(start..end, snippets[next(snippets.len())]);
+            assert_matches_full_tokenize(&buffer);
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod lexer;
 pub mod ast;
-pub mod parser;
\ No newline at end of file
+pub mod parser;
+pub mod printer;
\ No newline at end of file
@@ -1,5 +1,5 @@
 #![allow(clippy::redundant_closure_call)] //autogenerated parser code
-use crate::parsing::ast::{EnumVariant, Expr, Stmt};
+use crate::parsing::ast::{EnumVariant, Expr, Pattern, Stmt};
 use crate::parsing::lexer::{Token, TokenKind};
 
 macro_rules! t {
@@ -19,11 +19,14 @@ enum CallVariant {
     Partial(Vec<Option<Expr>>),
     Property(Token),
     PropertyTest(Token),
+    Index(Token, Expr),
+    Slice(Token, Option<Expr>, Option<Expr>),
 }
 
 enum AssignmentTarget {
     Variable(Token),
     Property(Expr),
+    Index(Expr, Token, Expr),
 }
 
 peg::parser! {
@@ -38,6 +41,7 @@ peg::parser! {
         rule stmt() -> Stmt =
 
              var_decl_stmt()
+            / const_decl_stmt()
             / function_decl_stmt()
             / struct_decl_stmt()
             / enum_decl_stmt()
@@ -50,9 +54,25 @@ peg::parser! {
 
 
         rule var_decl_stmt() -> Stmt =
-            [t!(Var)] n:name() e:assignment_right_side()?
+            [t!(Var)] p:destructuring_pattern() e:assignment_right_side()
+                {Stmt::DestructuringDeclaration(p, e)}
+            / [t!(Var)] n:name() e:assignment_right_side()?
                 {Stmt::VarDeclaration(n, e)}
 
+        rule const_decl_stmt() -> Stmt =
+            [t!(Const)] n:name() e:assignment_right_side()
+                {Stmt::ConstDeclaration(n, e)}
+
+        rule destructuring_pattern() -> Pattern =
+            [l@t!(LParen)] p:pattern() ++ [t!(Comma)] [t!(Comma)]? [t!(RParen)]
+                {Pattern::Tuple(l.clone(), p)}
+            / [l@t!(LBracket)] p:pattern() ++ [t!(Comma)] [t!(Comma)]? [t!(RBracket)]
+                {Pattern::List(l.clone(), p)}
+
+        rule pattern() -> Pattern =
+            destructuring_pattern()
+            / n:name() {Pattern::Name(n)}
+
         rule assignment_right_side() -> Expr =
             [t!(Equals)] e:expr() {e}
 
@@ -129,9 +149,11 @@ peg::parser! {
 
         rule paren_name_list() -> (Vec<Token>, Option<Token>) =
 
-            [t!(LParen)] n:name()**[t!(Comma)] [t!(Comma)] v:vararg() [t!(Comma)]?  [t!(RParen)] {(n, Some(v))}
+            [t!(LParen)] n:name() ++ [t!(Comma)] [t!(Comma)] v:vararg() [t!(Comma)]?  [t!(RParen)] {(n, Some(v))}
             /
-            [t!(LParen)] n:name()**[t!(Comma)] [t!(Comma)]?  [t!(RParen)] {(n, None)}
+            [t!(LParen)] n:name() ++ [t!(Comma)] [t!(Comma)]?  [t!(RParen)] {(n, None)}
+            /
+            [t!(LParen)] [t!(RParen)] {(vec![], None)}
             /
             [t!(LParen)] v:vararg() [t!(Comma)]? [t!(RParen)] {(vec![], Some(v))}
 
@@ -179,6 +201,9 @@ peg::parser! {
                     AssignmentTarget::Variable(v) => {
                         Stmt::Assignment(v, e)
                     }
+                    AssignmentTarget::Index(target, bracket, index) => {
+                        Stmt::IndexAssignment(target, bracket, index, e)
+                    }
                 }
 
                 }
@@ -188,6 +213,13 @@ peg::parser! {
             AssignmentTarget::Property(prop)
         }
         /
+        target: call() {?
+            match target {
+                Expr::Index(target, bracket, index) => Ok(AssignmentTarget::Index(*target, bracket, *index)),
+                _ => Err("index access")
+            }
+        }
+        /
 
         n:name() {
             AssignmentTarget::Variable(n)
@@ -195,7 +227,15 @@ peg::parser! {
 
 
         rule assert_stmt() -> Stmt =
-            [a@t!(Assert)] e:expr() {Stmt::Assert(a.clone(), e)}
+            [a@t!(Assert)] start:offset() e:expr() end:offset() m:assert_message()?
+                {Stmt::Assert(a.clone(), e, start..end, m)}
+
+        /// byte offset of the next token in source, without consuming it
+        rule offset() -> usize =
+            next:&[_] {next.position.2}
+
+        rule assert_message() -> Expr =
+            [t!(Comma)] m:expr() {m}
 
         rule if_expr() -> Expr =
             if_elif_else()/ if_elif() / if_then()
@@ -235,11 +275,17 @@ peg::parser! {
         rule expr() -> Expr =
             block_expr() /
             if_expr() /
+            try_expr() /
             simple_expr()
 
+        rule try_expr() -> Expr =
+            [t@t!(Try)] body:expr() [t!(LineEnd)]? [c@t!(Catch)] [t!(LParen)] e:name() [t!(RParen)] handler:expr() {
+                let handler = Expr::AnonFunction(vec![e], None, c.clone(), Box::new(handler));
+                Expr::TryCatch(t.clone(), Box::new(body), Box::new(handler))
+            }
+
         rule block_expr() -> Expr =
             b:block() {Expr::Block(b.0, b.1, b.2)}
-            / inline_block()
 
         rule simple_expr() -> Expr =
             arrow() /
@@ -298,9 +344,17 @@ peg::parser! {
             x: (@) [op@t!(Mod)] y:@
                 {bin!(op, x, y)}
             --
+            [op@t!(Minus)] x:@
+                {Expr::Unary(op.clone(), Box::new(x))}
+            [op@t!(Tilde)] x:@
+                {Expr::Unary(op.clone(), Box::new(x))}
+            --
             x:@ [op@t!(Power)] y:(@)
                 {bin!(op, x, y)}
             --
+            x:(@) [op@t!(As)] target:name()
+                {Expr::Cast(Box::new(x), op.clone(), target)}
+            --
             n:call() {n}
         }
 
@@ -330,6 +384,18 @@ peg::parser! {
                     CallVariant::PropertyTest(prop) => {
                         res = Expr::PropertyTest(Box::new(res), prop)
                     }
+
+                    CallVariant::Index(bracket, index) => {
+                        res = Expr::Index(Box::new(res), bracket, Box::new(index))
+                    }
+                    CallVariant::Slice(bracket, start, end) => {
+                        res = Expr::Slice(
+                            Box::new(res),
+                            bracket,
+                            start.map(Box::new),
+                            end.map(Box::new),
+                        )
+                    }
                 }
             }
                 res
@@ -339,10 +405,17 @@ peg::parser! {
         rule call_right_side() -> CallVariant =
             call_property_access()
             / call_parens()
+            / call_index()
+
+        rule call_index() -> CallVariant =
+            [b@t!(LBracket)] index:expr() [t!(RBracket)] {CallVariant::Index(b.clone(), index)}
+        / [b@t!(LBracket)] start:expr()? [t!(Colon)] end:expr()? [t!(RBracket)]
+            {CallVariant::Slice(b.clone(), start, end)}
 
         rule call_parens() -> CallVariant =
-            [t!(LParen)] args:expr()**[t!(Comma)] [t!(Comma)]? [t!(RParen)] {CallVariant::Normal(args)}
-        / [t!(LParen)] args:maybe_argument()**[t!(Comma)] [t!(Comma)]? [t!(RParen)] {
+            [t!(LParen)] [t!(RParen)] {CallVariant::Normal(vec![])}
+        / [t!(LParen)] args:expr() ++ [t!(Comma)] [t!(Comma)]? [t!(RParen)] {CallVariant::Normal(args)}
+        / [t!(LParen)] args:maybe_argument() ++ [t!(Comma)] [t!(Comma)]? [t!(RParen)] {
             CallVariant::Partial(args)
         }
 
@@ -362,7 +435,31 @@ peg::parser! {
             / t:name()
                 {Expr::Name(t)}
             / [s@t!(ConstString(..))] {Expr::ConstString(s.clone())}
+            / inline_block()
             / [t!(LParen)] e:expr() [t!(RParen)] {e}
+            / tuple_literal()
+            / map_literal()
+            / list_literal()
+
+        rule map_literal() -> Expr =
+            [l@t!(LBrace)] [t!(RBrace)] {Expr::MapLiteral(l.clone(), vec![])}
+            / [l@t!(LBrace)] entries:map_entry() ++ [t!(Comma)] [t!(Comma)]? [t!(RBrace)]
+                {Expr::MapLiteral(l.clone(), entries)}
+
+        rule list_literal() -> Expr =
+            [l@t!(LBracket)] [t!(RBracket)] {Expr::ListLiteral(l.clone(), vec![])}
+            / [l@t!(LBracket)] items:expr() ++ [t!(Comma)] [t!(Comma)]? [t!(RBracket)]
+                {Expr::ListLiteral(l.clone(), items)}
+
+        rule tuple_literal() -> Expr =
+            [l@t!(LParen)] first:expr() [t!(Comma)] rest:expr() ** [t!(Comma)] [t!(Comma)]? [t!(RParen)] {
+                let mut items = vec![first];
+                items.extend(rest);
+                Expr::TupleLiteral(l.clone(), items)
+            }
+
+        rule map_entry() -> (Expr, Expr) =
+            k:expr() [t!(Colon)] v:expr() {(k, v)}
 
 
 
@@ -370,3 +467,70 @@ peg::parser! {
             = [t@Token{kind:TokenKind::Name(..), position:pos}] {t.clone()}
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::program_parser;
+    use crate::parsing::ast::{Expr, Stmt};
+    use crate::parsing::lexer::tokenize;
+
+    fn parse(source: &str) -> Result<Vec<Stmt>, String> {
+        let tokens = tokenize(source)?;
+        let tokens = tokens.iter().collect::<Vec<_>>();
+        program_parser::program(&tokens).map_err(|e| e.to_string())
+    }
+
+    /// trailing comma is discarded, so source with comma replaced by space should produce
+    /// the same tree (positions included)
+    fn assert_same_tree(with_comma: &str, without_comma: &str) {
+        assert_eq!(
+            format!("{:?}", parse(with_comma).unwrap()),
+            format!("{:?}", parse(without_comma).unwrap())
+        );
+    }
+
+    #[test]
+    fn call_arguments_allow_trailing_comma() {
+        assert_same_tree("f(1, 2,)", "f(1, 2 )");
+        assert_same_tree("f(_, 2,)", "f(_, 2 )");
+    }
+
+    #[test]
+    fn parameters_allow_trailing_comma() {
+        assert_same_tree("def f(a, b,) = a", "def f(a, b ) = a");
+        assert_same_tree("def f(a, *b,) = a", "def f(a, *b ) = a");
+        assert_same_tree("var g = (a, b,) => a", "var g = (a, b ) => a");
+    }
+
+    #[test]
+    fn map_literal_allows_trailing_comma() {
+        assert_same_tree("var m = {1: 2, 3: 4,}", "var m = {1: 2, 3: 4 }");
+    }
+
+    #[test]
+    fn lone_comma_is_rejected() {
+        assert!(parse("f(,)").is_err());
+        assert!(parse("def f(,) = 1").is_err());
+        assert!(parse("var g = (,) => 1").is_err());
+        assert!(parse("def f(, *a) = 1").is_err());
+        assert!(parse("var m = {,}").is_err());
+    }
+
+    #[test]
+    fn repeated_comma_is_rejected() {
+        assert!(parse("f(1,, 2)").is_err());
+        assert!(parse("f(1, 2,,)").is_err());
+        assert!(parse("def f(a,,b) = a").is_err());
+        assert!(parse("var g = (a,,b) => a").is_err());
+    }
+
+    #[test]
+    fn empty_lists_are_still_allowed() {
+        match parse("f()").unwrap().as_slice() {
+            [Stmt::Expression(Expr::Call(_, args))] => assert!(args.is_empty()),
+            other => panic!("expected single call, got {:?}", other),
+        }
+        parse("def f() = 1").unwrap();
+        parse("var g = () => 1").unwrap();
+    }
+}
@@ -0,0 +1,714 @@
+use crate::parsing::ast::{EnumVariant, Expr, Pattern, Program, Stmt};
+use crate::parsing::lexer::{is_plain_name, Token, TokenKind};
+
+const INDENT: &str = "    ";
+
+/// binding strength of expression forms, mirrors `expr` and `arithmetic` rules of parser
+mod precedence {
+    /// `if` and indented blocks, only allowed where parser expects `expr`
+    pub const EXPR: u8 = 0;
+    pub const ARROW: u8 = 1;
+    pub const OR: u8 = 2;
+    pub const AND: u8 = 3;
+    pub const NOT: u8 = 4;
+    pub const EQUALITY: u8 = 5;
+    pub const COMPARISON: u8 = 6;
+    pub const SUM: u8 = 7;
+    pub const PRODUCT: u8 = 8;
+    /// unary minus, also negative number literals
+    pub const NEGATE: u8 = 9;
+    pub const POWER: u8 = 10;
+    pub const CAST: u8 = 11;
+    /// calls, property access, indexing and terms
+    pub const CALL: u8 = 12;
+}
+
+/// renders program as canonically formatted source which parses back into the same tree.
+///
+/// Blocks are indented where the layout allows it and written as `(s; s)` inside brackets
+/// and operands. Trees produced by rewriting passes may contain nodes parser never builds
+/// (e.g. one-statement blocks inside brackets), these are rendered on best effort basis.
+pub fn print_program(program: &Program) -> String {
+    let mut printer = Printer::default();
+    for stmt in program {
+        printer.stmt(stmt, true);
+        printer.output.push('\n');
+    }
+    printer.output
+}
+
+#[allow(dead_code)]
+pub fn print_stmt(stmt: &Stmt) -> String {
+    let mut printer = Printer::default();
+    printer.stmt(stmt, true);
+    printer.output
+}
+
+#[allow(dead_code)]
+pub fn print_expr(expr: &Expr) -> String {
+    let mut printer = Printer::default();
+    printer.expr(expr, precedence::EXPR, true);
+    printer.output
+}
+
+#[derive(Default)]
+struct Printer {
+    output: String,
+    indentation: usize,
+}
+
+impl Printer {
+    fn newline(&mut self) {
+        self.output.push('\n');
+        for _ in 0..self.indentation {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    fn token(&mut self, token: &Token) {
+        match &token.kind {
+            TokenKind::Name(name) if is_plain_name(name) => self.output.push_str(name),
+            TokenKind::Name(name) => {
+                self.output.push('`');
+                self.output.push_str(name);
+                self.output.push('`');
+            }
+            TokenKind::ConstString(s) => {
+                self.output.push('"');
+                for character in s.chars() {
+                    match character {
+                        '\n' => self.output.push_str("\\n"),
+                        '\t' => self.output.push_str("\\t"),
+                        '\r' => self.output.push_str("\\r"),
+                        '\0' => self.output.push_str("\\0"),
+                        '\\' => self.output.push_str("\\\\"),
+                        '"' => self.output.push_str("\\\""),
+                        other => self.output.push(other),
+                    }
+                }
+                self.output.push('"');
+            }
+            TokenKind::Number(n) => self.output.push_str(&n.to_string()),
+            TokenKind::FloatNumber(n) => {
+                //Display of f64 never uses exponent but drops fractional part of whole numbers
+                let number = n.to_string();
+                self.output.push_str(&number);
+                if !number.contains('.') {
+                    self.output.push_str(".0");
+                }
+            }
+            TokenKind::True => self.output.push_str("true"),
+            TokenKind::False => self.output.push_str("false"),
+            TokenKind::Mod => self.output.push_str("mod"),
+            other => self.output.push_str(&other.to_string()),
+        }
+    }
+
+    fn separated<T>(&mut self, items: &[T], separator: &str, mut f: impl FnMut(&mut Self, &T)) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(separator);
+            }
+            f(self, item);
+        }
+    }
+
+    fn parameters(&mut self, args: &[Token], vararg: &Option<Token>) {
+        self.output.push('(');
+        self.separated(args, ", ", |p, arg| p.token(arg));
+        if let Some(vararg) = vararg {
+            if !args.is_empty() {
+                self.output.push_str(", ");
+            }
+            self.output.push('*');
+            self.token(vararg);
+        }
+        self.output.push(')');
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Name(name) => self.token(name),
+            Pattern::Tuple(_, items) => {
+                self.output.push('(');
+                self.separated(items, ", ", Printer::pattern);
+                self.output.push(')');
+            }
+            Pattern::List(_, items) => {
+                self.output.push('[');
+                self.separated(items, ", ", Printer::pattern);
+                self.output.push(']');
+            }
+        }
+    }
+
+    /// names on separate indented lines, used by struct and enum declarations
+    fn field_list(&mut self, fields: &[Token]) {
+        if fields.is_empty() {
+            return;
+        }
+        self.output.push(':');
+        self.indentation += 1;
+        for field in fields {
+            self.newline();
+            self.token(field);
+        }
+        self.indentation -= 1;
+    }
+
+    fn indented_block(&mut self, body: &[Stmt]) {
+        self.indentation += 1;
+        for stmt in body {
+            self.newline();
+            self.stmt(stmt, true);
+        }
+        self.indentation -= 1;
+    }
+
+    fn inline_block(&mut self, body: &[Stmt]) {
+        self.output.push('(');
+        self.separated(body, "; ", |p, stmt| p.stmt(stmt, false));
+        self.output.push(')');
+    }
+
+    /// expression following a keyword or `=`, returns whether it was written as indented block
+    fn body(&mut self, expr: &Expr, min_precedence: u8, tail: bool) -> bool {
+        match expr {
+            Expr::Block(_, _, body) if tail => {
+                self.indented_block(body);
+                true
+            }
+            _ => {
+                self.output.push(' ');
+                self.expr(expr, min_precedence, tail);
+                false
+            }
+        }
+    }
+
+    /// `tail` tells whether nothing follows statement on its line, so indentation may be used
+    fn stmt(&mut self, stmt: &Stmt, tail: bool) {
+        match stmt {
+            Stmt::VarDeclaration(name, value) => {
+                self.output.push_str("var ");
+                self.token(name);
+                if let Some(value) = value {
+                    self.output.push_str(" =");
+                    self.body(value, precedence::EXPR, tail);
+                }
+            }
+            Stmt::DestructuringDeclaration(pattern, value) => {
+                self.output.push_str("var ");
+                self.pattern(pattern);
+                self.output.push_str(" =");
+                self.body(value, precedence::EXPR, tail);
+            }
+            Stmt::ConstDeclaration(name, value) => {
+                self.output.push_str("const ");
+                self.token(name);
+                self.output.push_str(" =");
+                self.body(value, precedence::EXPR, tail);
+            }
+            Stmt::Assignment(name, value) => {
+                self.token(name);
+                self.output.push_str(" =");
+                self.body(value, precedence::EXPR, tail);
+            }
+            Stmt::PropertyAssignment(target, value) => {
+                self.expr(target, precedence::CALL, false);
+                self.output.push_str(" =");
+                self.body(value, precedence::EXPR, tail);
+            }
+            Stmt::IndexAssignment(target, _, index, value) => {
+                self.expr(target, precedence::CALL, false);
+                self.output.push('[');
+                self.expr(index, precedence::EXPR, false);
+                self.output.push_str("] =");
+                self.body(value, precedence::EXPR, tail);
+            }
+            Stmt::Expression(expr) => self.expr(expr, precedence::EXPR, tail),
+            Stmt::Assert(_, condition, _, message) => {
+                self.output.push_str("assert");
+                self.body(condition, precedence::EXPR, tail && message.is_none());
+                if let Some(message) = message {
+                    self.output.push(',');
+                    self.body(message, precedence::EXPR, tail);
+                }
+            }
+            Stmt::Pass(_) => self.output.push_str("pass"),
+            Stmt::FunctionDeclaration {
+                name,
+                args,
+                vararg,
+                body,
+            } => {
+                self.output.push_str("def ");
+                self.token(name);
+                if !args.is_empty() || vararg.is_some() {
+                    self.parameters(args, vararg);
+                }
+                self.output.push_str(" =");
+                self.body(body, precedence::EXPR, tail);
+            }
+            Stmt::StructDeclaration { name, fields } => {
+                self.output.push_str("struct ");
+                self.token(name);
+                self.field_list(fields);
+            }
+            Stmt::EnumDeclaration { name, variants } => {
+                self.output.push_str("enum ");
+                self.token(name);
+                if !variants.is_empty() {
+                    self.output.push(':');
+                    self.indentation += 1;
+                    for EnumVariant { name, fields } in variants {
+                        self.newline();
+                        self.token(name);
+                        self.field_list(fields);
+                    }
+                    self.indentation -= 1;
+                }
+            }
+            Stmt::ImplBlock {
+                name,
+                implementations,
+            } => {
+                self.output.push_str("impl ");
+                self.token(name);
+                self.output.push(':');
+                self.indented_block(implementations);
+            }
+            Stmt::Import {
+                module,
+                name,
+                rename,
+            } => {
+                self.output.push_str("import ");
+                for item in module {
+                    self.token(item);
+                    self.output.push('.');
+                }
+                self.token(name);
+                if let Some(rename) = rename {
+                    self.output.push_str(" as ");
+                    self.token(rename);
+                }
+            }
+        }
+    }
+
+    fn precedence(expr: &Expr) -> u8 {
+        match expr {
+            Expr::If(..) | Expr::TryCatch(..) | Expr::SingleStatement(..) => precedence::EXPR,
+            Expr::AnonFunction(..) => precedence::ARROW,
+            Expr::Binary(op, ..) => Printer::binary_precedence(op),
+            Expr::Unary(op, _) if op.kind != TokenKind::Not => precedence::NEGATE,
+            Expr::Unary(..) => precedence::NOT,
+            Expr::Number(n) if n.get_number().unwrap() < 0 => precedence::NEGATE,
+            Expr::FloatNumber(f) if f.get_float().unwrap().is_sign_negative() => precedence::NEGATE,
+            Expr::Cast(..) => precedence::CAST,
+            _ => precedence::CALL,
+        }
+    }
+
+    fn binary_precedence(op: &Token) -> u8 {
+        match op.kind {
+            TokenKind::Or => precedence::OR,
+            TokenKind::And => precedence::AND,
+            TokenKind::CompareEquals | TokenKind::CompareNotEquals => precedence::EQUALITY,
+            TokenKind::CompareGreater
+            | TokenKind::CompareGreaterEqual
+            | TokenKind::CompareLess
+            | TokenKind::CompareLessEqual => precedence::COMPARISON,
+            TokenKind::Plus | TokenKind::Minus => precedence::SUM,
+            TokenKind::Star | TokenKind::Slash | TokenKind::Mod => precedence::PRODUCT,
+            TokenKind::Power => precedence::POWER,
+            _ => unreachable!("{} is not a binary operator [{}]", op.kind, op.position),
+        }
+    }
+
+    /// writes expression, parenthesizing it if it binds weaker than `min_precedence`
+    fn expr(&mut self, expr: &Expr, min_precedence: u8, tail: bool) {
+        if Printer::precedence(expr) < min_precedence {
+            self.output.push('(');
+            self.expr(expr, precedence::EXPR, false);
+            self.output.push(')');
+            return;
+        }
+
+        match expr {
+            Expr::Number(t)
+            | Expr::FloatNumber(t)
+            | Expr::Bool(t)
+            | Expr::Name(t)
+            | Expr::ConstString(t) => self.token(t),
+            Expr::Binary(op, left, right) => {
+                let own = Printer::binary_precedence(op);
+                //power is the only right associative operator
+                let (left_min, right_min) = if op.kind == TokenKind::Power {
+                    (own + 1, own)
+                } else {
+                    (own, own + 1)
+                };
+                self.expr(left, left_min, false);
+                self.output.push(' ');
+                self.token(op);
+                self.output.push(' ');
+                self.expr(right, right_min, false);
+            }
+            Expr::Unary(op, operand) if op.kind != TokenKind::Not => {
+                self.token(op);
+                self.expr(operand, precedence::NEGATE, false);
+            }
+            Expr::Unary(op, operand) => {
+                self.token(op);
+                self.output.push(' ');
+                self.expr(operand, precedence::NOT, false);
+            }
+            Expr::If(condition, then, otherwise) => {
+                self.if_expr(condition, then, otherwise.as_deref(), tail)
+            }
+            Expr::Block(_, _, body) => {
+                if tail {
+                    self.indented_block(body)
+                } else {
+                    self.inline_block(body)
+                }
+            }
+            Expr::SingleStatement(stmt) => self.stmt(stmt, tail),
+            Expr::Call(target, args) => {
+                self.expr(target, precedence::CALL, false);
+                self.output.push('(');
+                self.separated(args, ", ", |p, arg| p.expr(arg, precedence::EXPR, false));
+                self.output.push(')');
+            }
+            Expr::PartialCall(target, args) => {
+                self.expr(target, precedence::CALL, false);
+                self.output.push('(');
+                self.separated(args, ", ", |p, arg| match arg {
+                    Some(arg) => p.expr(arg, precedence::EXPR, false),
+                    None => p.output.push('_'),
+                });
+                self.output.push(')');
+            }
+            Expr::AnonFunction(args, vararg, _, body) => {
+                self.parameters(args, vararg);
+                self.output.push_str(" => ");
+                self.expr(body, precedence::ARROW, false);
+            }
+            Expr::PropertyAccess(target, property) => {
+                //lexer would read `12.` as start of float number
+                if let Expr::Number(number) = target.as_ref() {
+                    self.output.push('(');
+                    self.token(number);
+                    self.output.push(')');
+                } else {
+                    self.expr(target, precedence::CALL, false);
+                }
+                self.output.push('.');
+                self.token(property);
+            }
+            Expr::PropertyTest(target, property) => {
+                self.expr(target, precedence::CALL, false);
+                self.output.push('?');
+                self.token(property);
+            }
+            Expr::MapLiteral(_, entries) => {
+                self.output.push('{');
+                self.separated(entries, ", ", |p, (key, value)| {
+                    p.expr(key, precedence::EXPR, false);
+                    p.output.push_str(": ");
+                    p.expr(value, precedence::EXPR, false);
+                });
+                self.output.push('}');
+            }
+            Expr::ListLiteral(_, items) => {
+                self.output.push('[');
+                self.separated(items, ", ", |p, item| p.expr(item, precedence::EXPR, false));
+                self.output.push(']');
+            }
+            Expr::TupleLiteral(_, items) => {
+                self.output.push('(');
+                self.separated(items, ", ", |p, item| p.expr(item, precedence::EXPR, false));
+                if items.len() == 1 {
+                    self.output.push(',');
+                }
+                self.output.push(')');
+            }
+            Expr::Index(target, _, index) => {
+                self.expr(target, precedence::CALL, false);
+                self.output.push('[');
+                self.expr(index, precedence::EXPR, false);
+                self.output.push(']');
+            }
+            Expr::Slice(target, _, start, end) => {
+                self.expr(target, precedence::CALL, false);
+                self.output.push('[');
+                if let Some(start) = start {
+                    self.expr(start, precedence::EXPR, false);
+                }
+                self.output.push(':');
+                if let Some(end) = end {
+                    self.expr(end, precedence::EXPR, false);
+                }
+                self.output.push(']');
+            }
+            Expr::Cast(value, _, target) => {
+                self.expr(value, precedence::CAST, false);
+                self.output.push_str(" as ");
+                self.token(target);
+            }
+            Expr::TryCatch(_, body, handler) => self.try_expr(body, handler, tail),
+        }
+    }
+
+    /// whether expression would be written starting with parenthesized expression, parameter
+    /// list or list literal, which parser would take for call arguments or index if written
+    /// after a term
+    fn starts_with_brackets(expr: &Expr, min_precedence: u8) -> bool {
+        if Printer::precedence(expr) < min_precedence {
+            return true;
+        }
+        match expr {
+            Expr::AnonFunction(..) | Expr::ListLiteral(..) | Expr::TupleLiteral(..) => true,
+            Expr::Binary(op, left, _) => {
+                let own = Printer::binary_precedence(op);
+                let left_min = if op.kind == TokenKind::Power {
+                    own + 1
+                } else {
+                    own
+                };
+                Printer::starts_with_brackets(left, left_min)
+            }
+            Expr::Cast(value, ..) => Printer::starts_with_brackets(value, precedence::CAST),
+            Expr::Call(target, _)
+            | Expr::PartialCall(target, _)
+            | Expr::PropertyAccess(target, _)
+            | Expr::PropertyTest(target, _)
+            | Expr::Index(target, ..)
+            | Expr::Slice(target, ..) => Printer::starts_with_brackets(target, precedence::CALL),
+            _ => false,
+        }
+    }
+
+    /// branch following condition of `if` or `elif`. Branch that would start with brackets
+    /// is moved to indented block (when layout allows it) so that it is not read as call
+    /// of condition, which wraps it into block in resulting tree
+    fn conditional_branch(&mut self, then: &Expr, min_precedence: u8, tail: bool) -> bool {
+        if tail && Printer::starts_with_brackets(then, min_precedence) {
+            self.indented_block(&[Stmt::Expression(then.clone())]);
+            true
+        } else {
+            self.body(then, min_precedence, tail)
+        }
+    }
+
+    /// `if`/`elif`/`else` chain, nested `if` without `else` is parenthesized to keep
+    /// following `else` from attaching to it
+    fn if_expr(&mut self, condition: &Expr, then: &Expr, otherwise: Option<&Expr>, tail: bool) {
+        let branch_precedence = |otherwise: Option<&Expr>| match otherwise {
+            Some(_) => precedence::ARROW,
+            None => precedence::EXPR,
+        };
+
+        self.output.push_str("if ");
+        self.expr(condition, precedence::ARROW, false);
+        let mut indented = self.conditional_branch(then, branch_precedence(otherwise), tail);
+
+        let mut otherwise = otherwise;
+        while let Some(branch) = otherwise {
+            if indented {
+                self.newline();
+            } else {
+                self.output.push(' ');
+            }
+            match branch {
+                Expr::If(condition, then, next) => {
+                    self.output.push_str("elif ");
+                    self.expr(condition, precedence::ARROW, false);
+                    indented =
+                        self.conditional_branch(then, branch_precedence(next.as_deref()), tail);
+                    otherwise = next.as_deref();
+                }
+                other => {
+                    self.output.push_str("else");
+                    self.body(other, precedence::EXPR, tail);
+                    otherwise = None;
+                }
+            }
+        }
+    }
+
+    /// `try`/`catch`, handler is stored as function of error and printed as its body
+    fn try_expr(&mut self, body: &Expr, handler: &Expr, tail: bool) {
+        let (error, handler) = match handler {
+            Expr::AnonFunction(args, None, _, handler) if args.len() == 1 => (&args[0], handler),
+            _ => unreachable!("handler of try is always function of error"),
+        };
+
+        self.output.push_str("try");
+        if self.body(body, precedence::ARROW, tail) {
+            self.newline();
+        } else {
+            self.output.push(' ');
+        }
+        self.output.push_str("catch (");
+        self.token(error);
+        self.output.push(')');
+        self.body(handler, precedence::EXPR, tail);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{print_expr, print_program};
+    use crate::parsing::ast::{Expr, Stmt};
+    use crate::parsing::lexer::tokenize;
+    use crate::parsing::parser::program_parser;
+    use regex::Regex;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let tokens = tokenize(source).unwrap();
+        let tokens = tokens.iter().collect::<Vec<_>>();
+        program_parser::program(&tokens).unwrap()
+    }
+
+    /// debug representation of tree without token positions, source ranges and block
+    /// delimiters, which only tell inline blocks from indented ones
+    fn shape(program: &[Stmt]) -> String {
+        let position = Regex::new(r"position: Index\(\d+, \d+, \d+\), ").unwrap();
+        let range = Regex::new(r"\d+\.\.\d+, ").unwrap();
+        let delimiters =
+            Regex::new(r"Block\(Token \{ kind: \w+ \}, Token \{ kind: \w+ \}, ").unwrap();
+        let tree = format!("{:?}", program);
+        let tree = position.replace_all(&tree, "");
+        let tree = range.replace_all(&tree, "");
+        delimiters.replace_all(&tree, "Block(").into_owned()
+    }
+
+    fn assert_round_trip(source: &str) -> String {
+        let tree = parse(source);
+        let printed = print_program(&tree);
+        let reparsed = parse(&printed);
+        assert_eq!(shape(&tree), shape(&reparsed), "printed as:\n{}", printed);
+        assert_eq!(printed, print_program(&reparsed));
+        printed
+    }
+
+    #[test]
+    fn representative_program_survives_round_trip() {
+        assert_round_trip(
+            r#"
+import lib.math.sqrt as root
+struct Point:
+    x
+    y
+enum Shape:
+    Circle:
+        radius
+    Empty
+impl Point:
+    def norm(self) = root(self.x ** 2 + self.y ** 2)
+    def scaled(self, k) =
+        var p = Point(self.x * k, self.y * k)
+        p
+const limit = 10
+var (a, [b, c]) = list(1, list(2, 3))
+var m = {"a": 1, 2: 3.5,}
+var l = [[], [1, m], m["a"],]
+m["b"] = m["a"] as Float
+var point = Point(1, 2)
+point.x = 5
+def sum(first, *rest) =
+    if first > limit
+        var excess = first - limit
+        excess
+    elif first == 0 0
+    else
+        first + len(rest) mod 2
+var g = (x, *more) => x and not more?empty or false
+var h = sum(_, 2)
+assert (var t = 1; t + 1) == 2, "inline blocks"
+assert `name with spaces` != 0
+print(if a b else c, {}, xs[1:], xs[:n + 1], xs[:], (1, 2), ("single",))
+print((12)._mod(5), "tab\t \"quoted\"", r"C:\raw")
+pass
+"#,
+        );
+    }
+
+    #[test]
+    fn parentheses_are_kept_only_where_needed() {
+        assert_eq!(
+            assert_round_trip("((1 + 2)) * (3 - (4 - 5)) + 2 ** (3 ** 4)"),
+            "(1 + 2) * (3 - (4 - 5)) + 2 ** 3 ** 4\n"
+        );
+        assert_eq!(
+            assert_round_trip("(2 ** 3) ** 4 == (not a) and (b or c)"),
+            "(2 ** 3) ** 4 == (not a) and (b or c)\n"
+        );
+        assert_eq!(assert_round_trip("((x) => x)(1).y"), "((x) => x)(1).y\n");
+        assert_eq!(
+            assert_round_trip("(-2) ** 2 + -(2 ** 2) - -x * - -1"),
+            "(-2) ** 2 + -2 ** 2 - -x * --1\n"
+        );
+        assert_eq!(assert_round_trip("~(~x) + (~y) ** 2"), "~~x + (~y) ** 2\n");
+        assert_eq!(
+            assert_round_trip("(1 + 2 as Float) as String"),
+            "(1 + 2 as Float) as String\n"
+        );
+    }
+
+    #[test]
+    fn nested_if_keeps_else_attached() {
+        assert_eq!(
+            assert_round_trip("res = if c1 if c2 1 else 2"),
+            "res = if c1 if c2 1 else 2\n"
+        );
+        assert_round_trip("res = (if c1 1 else 2) + 1");
+        assert_round_trip("res = if c1 (a; b) else c");
+
+        //`if c1 (if c2 1) else 3` would be a call of `c1`, so branch goes to separate line
+        let tree = parse("res = if c1\n    if c2 1\nelse 3");
+        let printed = print_program(&tree);
+        assert_eq!(printed, "res = if c1\n    if c2 1\nelse 3\n");
+        assert_eq!(shape(&tree), shape(&parse(&printed)));
+    }
+
+    #[test]
+    fn try_catch_survives_round_trip() {
+        assert_eq!(
+            assert_round_trip("var r = try 1 / 0 catch (e) e"),
+            "var r = try 1 / 0 catch (e) e\n"
+        );
+        assert_eq!(
+            assert_round_trip(
+                "var r = try\n    var x = 1\n    x / 0\ncatch (e)\n    print(e)\n    0"
+            ),
+            "var r = try\n    var x = 1\n    x / 0\ncatch (e)\n    print(e)\n    0\n"
+        );
+        assert_round_trip("f(try a catch (e) b, (try c catch (e) d) + 1)");
+    }
+
+    #[test]
+    fn branch_starting_with_parens_is_not_read_as_call() {
+        let expr = |source: &str| match parse(source).pop() {
+            Some(Stmt::Expression(e)) => e,
+            other => panic!("expected expression but got {:?}", other),
+        };
+        let tree = Expr::If(
+            Box::new(expr("c")),
+            Box::new(expr("(x) => x")),
+            Some(Box::new(expr("0"))),
+        );
+        assert_eq!(print_expr(&tree), "if c\n    (x) => x\nelse 0");
+    }
+
+    #[test]
+    fn blocks_are_indented_consistently() {
+        assert_eq!(
+            assert_round_trip("def f(x) =\n  if x\n      1\n  else\n   var y = 2\n   y\n"),
+            "def f(x) =\n    if x\n        1\n    else\n        var y = 2\n        y\n"
+        );
+    }
+}
@@ -1,6 +1,36 @@
-use super::execution::module::{compile_file, run_file};
+use super::execution::module::{compile_file, compile_program, run_file, Module};
+use crate::data::gc::GC;
+use crate::data::objects::Value;
+use crate::execution::builtins::builtin_factory;
+use crate::execution::vm::{InterpretError, VM};
 use std::path::Path;
 
+/// runs `f` with fresh VM that has default builtins and heap
+fn with_vm<R>(f: impl FnOnce(&mut VM) -> R) -> R {
+    with_gc_vm(unsafe { GC::default_gc() }, f)
+}
+
+/// same as [`with_vm`] but with given heap, e.g. one collecting on every allocation
+fn with_gc_vm<R>(mut gc: GC, f: impl FnOnce(&mut VM) -> R) -> R {
+    let builtins = builtin_factory();
+    let mut vm = VM::new(&mut gc, &builtins);
+    f(&mut vm)
+}
+
+/// module that [`compile`] and [`run`] put programs into
+fn test_module() -> Module {
+    Module::from_dot_notation("test")
+}
+
+fn compile(vm: &mut VM, source: &str) -> Value {
+    compile_program(source.to_string(), &test_module(), vm).unwrap()
+}
+
+fn run(vm: &mut VM, source: &str) -> Result<Value, InterpretError> {
+    let pointer = compile(vm, source);
+    vm.run(pointer)
+}
+
 macro_rules! test_file {
     ($name:ident) => {
         #[test]
@@ -35,20 +65,12 @@ macro_rules! test_fail_compile {
     ($name:ident) => {
         #[test]
         fn $name() {
-            use crate::data::gc::GC;
-            use crate::execution::builtins::builtin_factory;
-            use crate::execution::vm::VM;
             let mut path = String::new();
             path.push_str("examples/");
             path.push_str(stringify!($name));
             path.push_str(".txt");
 
-            let mut gc = unsafe { GC::default_gc() };
-            let builtins = builtin_factory();
-
-            let mut vm = VM::new(&mut gc, &builtins);
-
-            compile_file(Path::new(&path), &mut vm).err().unwrap();
+            with_vm(|vm| compile_file(Path::new(&path), vm).err().unwrap());
         }
     };
 }
@@ -93,6 +115,20 @@ test_file! {partials}
 
 test_file! {structures}
 
+test_file! {associated_functions}
+
+test_fail_file! {fail_associated_function_on_instance}
+
+test_file! {try_catch}
+
+test_file! {operator_overloading}
+
+test_fail_file! {fail_missing_operator_method}
+
+test_fail_file! {fail_bitwise_not_bool}
+
+test_fail_file! {fail_uncaught_stack_overflow}
+
 test_file! {strings}
 
 test_file! {builtins}
@@ -104,3 +140,631 @@ test_file! {enums}
 test_file! {inline_blocks}
 
 test_file! {imports}
+
+test_file! {destructuring}
+
+test_fail_file! {fail_destructuring_shape_mismatch}
+
+test_file! {consts}
+
+test_fail_compile! {fail_const_reassignment}
+
+test_fail_compile! {fail_const_not_constant}
+
+test_fail_compile! {fail_const_assignment_in_function}
+
+test_fail_compile! {fail_call_int_literal}
+
+test_fail_compile! {fail_partial_call_string_literal}
+
+test_fail_file! {fail_string_multiplication}
+test_fail_file! {fail_string_repetition_overflow}
+
+test_file! {maps}
+
+test_fail_file! {fail_map_missing_key}
+
+test_file! {assertion_messages}
+
+test_fail_file! {fail_assertion_with_message}
+
+test_fail_compile! {fail_assertion_message_not_string}
+
+#[test]
+fn failed_assertion_reports_message() {
+    let error = run_file(Path::new("examples/fail_assertion_with_message.txt")).unwrap_err();
+    assert!(error.to_string().contains("n is too small"));
+    assert!(error.to_string().contains("at line 2"));
+}
+
+test_fail_file! {fail_assertion_source}
+
+#[test]
+fn failed_assertion_without_message_reports_its_source() {
+    let error = run_file(Path::new("examples/fail_assertion_source.txt")).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("assertion failed: `(double(n))   ==  7`"));
+    assert!(error.to_string().contains("at line 3"));
+}
+
+test_file! {casts}
+
+test_fail_file! {fail_cast_bool_to_int}
+
+test_fail_compile! {fail_cast_unknown_target}
+
+test_file! {index_assignment}
+
+test_fail_file! {fail_index_assignment_out_of_range}
+
+test_fail_compile! {fail_index_assignment_to_const}
+
+test_file! {indexing}
+
+#[test]
+fn index_out_of_range_reports_index_length_and_line() {
+    let error = run_file(Path::new("examples/fail_index_out_of_range.txt")).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("IndexOutOfBounds { index: 3, length: 3 }"));
+    assert!(error.to_string().contains("at line 3"));
+}
+
+test_file! {block_as_value}
+
+test_file! {list_literals}
+
+test_file! {slices}
+
+test_fail_file! {fail_slice_bound}
+
+#[test]
+fn slice_bound_must_be_int() {
+    let error = run_file(Path::new("examples/fail_slice_bound.txt")).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("expected Int as slice bound, got String"));
+    assert!(error.to_string().contains("at line 2"));
+}
+
+test_file! {tuples}
+
+test_fail_file! {fail_tuple_destructuring_length}
+
+#[test]
+fn tuple_destructuring_checks_length() {
+    let error = run_file(Path::new("examples/fail_tuple_destructuring_length.txt")).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("cannot destructure Vector of 2 elements into 3 names"));
+}
+
+test_file! {negation}
+
+test_fail_file! {fail_negation_overflow}
+
+test_fail_compile! {fail_possibly_unassigned}
+
+test_fail_compile! {fail_possibly_unassigned_in_try}
+
+test_fail_file! {fail_too_few_args}
+
+test_fail_file! {fail_too_many_args}
+
+test_fail_file! {fail_too_few_partial_args}
+
+test_fail_file! {fail_too_few_vararg_args}
+
+#[test]
+fn arity_mismatch_tells_too_few_from_too_many() {
+    let error = run_file(Path::new("examples/fail_too_few_args.txt")).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("too few arguments: expected 2 args but got 1"));
+
+    let error = run_file(Path::new("examples/fail_too_many_args.txt")).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("too many arguments: expected 2 args but got 3"));
+
+    let error = run_file(Path::new("examples/fail_too_few_vararg_args.txt")).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("too few arguments: expected at least 2 args but got 1"));
+
+    let error = run_file(Path::new("examples/fail_too_few_partial_args.txt")).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("too few arguments: expected 2 args but got 1"));
+}
+
+mod timeout {
+    use super::{compile, with_vm};
+    use crate::execution::vm::{ExecutionBudget, InterpretErrorKind};
+    use std::time::Duration;
+
+    const INFINITE_PROGRAM: &str = "def spin(n) = spin(n + 1)\nspin(0)";
+
+    fn run_infinite_program(budget: ExecutionBudget) -> InterpretErrorKind {
+        run_program(INFINITE_PROGRAM, budget)
+    }
+
+    fn run_program(source: &str, budget: ExecutionBudget) -> InterpretErrorKind {
+        with_vm(|vm| {
+            let pointer = compile(vm, source);
+            vm.run_with_timeout(pointer, budget).unwrap_err().kind
+        })
+    }
+
+    #[test]
+    fn infinite_program_should_exceed_instruction_budget() {
+        let budget = ExecutionBudget {
+            max_instructions: Some(100_000),
+            max_duration: None,
+        };
+        assert_eq!(run_infinite_program(budget), InterpretErrorKind::Timeout);
+    }
+
+    #[test]
+    fn infinite_program_should_exceed_time_budget() {
+        let budget = ExecutionBudget {
+            max_instructions: None,
+            max_duration: Some(Duration::from_millis(50)),
+        };
+        assert_eq!(run_infinite_program(budget), InterpretErrorKind::Timeout);
+    }
+
+    #[test]
+    fn timeout_should_not_be_caught() {
+        let budget = ExecutionBudget {
+            max_instructions: Some(100_000),
+            max_duration: None,
+        };
+        let source = "def spin(n) = spin(n + 1)\ntry spin(0) catch (e) 0";
+        assert_eq!(run_program(source, budget), InterpretErrorKind::Timeout);
+    }
+}
+
+mod output {
+    use super::{run, with_vm};
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+
+    /// sink that stays readable after being handed over to VM
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn print_should_write_into_configured_sink() {
+        let buffer = SharedBuffer::default();
+        with_vm(|vm| {
+            vm.set_output(Box::new(buffer.clone()));
+            run(vm, "print(1, \"two\")\nprint()\nprint(1 + 2)").unwrap();
+        });
+
+        let output = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        assert_eq!(output, "1 two\n\n3\n");
+    }
+}
+
+mod snapshots {
+    use super::{run, with_gc_vm, with_vm};
+    use crate::data::gc::GC;
+    use crate::execution::vm::InterpretErrorKind;
+
+    #[test]
+    fn restore_should_drop_later_globals_and_keep_earlier_ones() {
+        //collect on every allocation to check that snapshot keeps its values alive
+        with_gc_vm(unsafe { GC::new(1) }, |vm| {
+            run(vm, "var kept = list(1, 2, \"long enough string\")").unwrap();
+            vm.push_snapshot();
+
+            run(
+                vm,
+                "kept = list(3)\nvar later = list(list(4), list(5))\nassert later[1] == list(5)",
+            )
+            .unwrap();
+
+            assert!(vm.restore_snapshot());
+            run(vm, "assert kept == list(1, 2, \"long enough string\")").unwrap();
+            assert_eq!(
+                run(vm, "later").unwrap_err().kind,
+                InterpretErrorKind::NameError {
+                    name: "later".to_string()
+                }
+            );
+            assert!(!vm.restore_snapshot());
+        });
+    }
+
+    #[test]
+    fn discarded_snapshot_should_keep_current_globals() {
+        with_vm(|vm| {
+            run(vm, "var a = 1").unwrap();
+            vm.push_snapshot();
+            vm.push_snapshot();
+            run(vm, "a = 2").unwrap();
+
+            assert!(vm.discard_snapshot());
+            run(vm, "assert a == 2").unwrap();
+            assert!(vm.restore_snapshot());
+            run(vm, "assert a == 1").unwrap();
+        });
+    }
+}
+
+test_file! {struct_equality}
+
+test_fail_compile! {fail_duplicate_method}
+
+test_fail_compile! {fail_method_field_clash}
+
+mod globals {
+    use super::{run, test_module, with_vm};
+    use crate::data::objects::Value;
+    use crate::execution::module::Module;
+
+    #[test]
+    fn globals_should_list_top_level_bindings() {
+        with_vm(|vm| {
+            let program = "var count = 1\nconst flag = true\ndef twice(x) = x * 2\n\
+                           count = twice(21)\nvar local = (var hidden = 1; hidden)";
+            run(vm, program).unwrap();
+
+            let mut globals = vm
+                .globals(&test_module())
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect::<Vec<_>>();
+            globals.sort_by(|a, b| a.0.cmp(&b.0));
+
+            let names = globals.iter().map(|g| g.0.as_str()).collect::<Vec<_>>();
+            assert_eq!(names, vec!["count", "flag", "local", "twice"]);
+            assert_eq!(globals[0].1, Value::Int(42));
+            assert_eq!(globals[1].1, Value::Bool(true));
+            assert_eq!(globals[2].1, Value::Int(1));
+            assert_eq!(globals[3].1.type_string(), "Function");
+
+            assert_eq!(vm.globals(&Module::from_dot_notation("missing")).count(), 0);
+        });
+    }
+}
+
+mod chunk_position {
+    use super::{compile, with_vm};
+    use crate::parsing::lexer::Index;
+
+    #[test]
+    fn top_level_chunk_should_be_positioned_at_source_start() {
+        with_vm(|vm| {
+            let pointer = compile(vm, "\nvar a = 1");
+            let chunk = pointer.unwrap_function().unwrap();
+            assert_eq!(chunk.name.position, Index(1, 1, 0));
+            assert!(chunk.to_string().lines().next().unwrap().contains("at 1:1"));
+        });
+    }
+}
+
+mod coercion {
+    use super::with_vm;
+    use crate::data::objects::Value;
+    use crate::execution::vm::CoercionPolicy;
+
+    #[test]
+    fn bool_in_arithmetic_should_follow_policy() {
+        with_vm(|vm| {
+            assert!(vm.eval_expr("true + 1").is_err());
+
+            assert_eq!(
+                vm.set_coercion_policy(CoercionPolicy::Permissive),
+                CoercionPolicy::Strict
+            );
+            assert_eq!(vm.eval_expr("true + 1").unwrap(), Value::Int(2));
+            assert_eq!(vm.eval_expr("2.5 * false").unwrap(), Value::Float(0.0));
+            assert!(vm.eval_expr("1 / false").is_err());
+            //comparisons are not arithmetic
+            assert_eq!(vm.eval_expr("true == 1").unwrap(), Value::Bool(false));
+        });
+    }
+}
+
+mod eval {
+    use super::with_vm;
+    use crate::data::objects::Value;
+
+    #[test]
+    fn expression_should_evaluate_to_its_value() {
+        with_vm(|vm| {
+            assert_eq!(vm.eval_expr("1 + 2").unwrap(), Value::Int(3));
+            assert_eq!(
+                vm.eval_expr("[1, 2, 3][1:] == [2, 3]").unwrap(),
+                Value::Bool(true)
+            );
+        });
+    }
+
+    #[test]
+    fn errors_of_every_stage_should_be_reported() {
+        with_vm(|vm| {
+            assert!(vm.eval_expr("1 +").is_err());
+            let error = vm.eval_expr("var x = 1").unwrap_err();
+            assert_eq!(error.to_string(), "expected expression, got statement");
+            let error = vm.eval_expr("1\n2").unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "expected single expression, got 2 statements"
+            );
+            let error = vm.eval_expr("1 + \"a\"").unwrap_err();
+            assert!(error.to_string().contains("at line 1"));
+        });
+    }
+}
+
+mod arity {
+    use super::with_vm;
+    use crate::execution::vm::VM;
+
+    #[test]
+    fn arity_of_callables_should_be_introspectable() {
+        let arity = |vm: &mut VM, source: &str| {
+            let value = vm.eval_expr(source).unwrap();
+            value.get_arity(vm).map(|arity| arity.to_string())
+        };
+
+        with_vm(|vm| {
+            assert_eq!(arity(vm, "(a, b) => a").as_deref(), Some("2"));
+            assert_eq!(arity(vm, "(a, *rest) => a").as_deref(), Some("at least 1"));
+            assert_eq!(arity(vm, "((a, b, c) => a)(_, 1, _)").as_deref(), Some("2"));
+            assert_eq!(arity(vm, "sum").as_deref(), Some("at least 0"));
+            assert_eq!(arity(vm, "1"), None);
+        });
+    }
+}
+
+mod block_value {
+    use super::with_vm;
+    use crate::data::objects::Value;
+
+    #[test]
+    fn block_should_evaluate_to_its_last_expression() {
+        with_vm(|vm| {
+            assert_eq!(vm.eval_expr("(var x = 1; x + 1)").unwrap(), Value::Int(2));
+        });
+    }
+
+    #[test]
+    fn block_ending_in_declaration_should_evaluate_to_nothing() {
+        with_vm(|vm| {
+            assert_eq!(
+                vm.eval_expr("(var x = 1; var y = x)").unwrap(),
+                Value::Nothing
+            );
+            assert_eq!(
+                vm.eval_expr("(var x = 1; def f = x)").unwrap(),
+                Value::Nothing
+            );
+        });
+    }
+
+    #[test]
+    fn block_ending_in_assignment_should_evaluate_to_nothing() {
+        with_vm(|vm| {
+            assert_eq!(vm.eval_expr("(var x = 1; x = 2)").unwrap(), Value::Nothing);
+        });
+    }
+}
+
+mod call_function {
+    use super::{run, test_module, with_gc_vm, with_vm};
+    use crate::data::gc::GC;
+    use crate::data::objects::Value;
+    use crate::execution::module::Module;
+    use crate::execution::vm::{InterpretErrorKind, VM};
+
+    const PROGRAM: &str = "def add(a, b) = a + b\ndef second(xs) = xs[1]";
+
+    fn call_error(
+        vm: &mut VM,
+        module: &Module,
+        name: &str,
+        args: Vec<Value>,
+    ) -> InterpretErrorKind {
+        vm.call_function(module, name, args).unwrap_err().kind
+    }
+
+    #[test]
+    fn function_should_be_called_with_rust_arguments() {
+        with_vm(|vm| {
+            run(vm, PROGRAM).unwrap();
+
+            let result = vm
+                .call_function(&test_module(), "add", vec![Value::Int(1), Value::Int(2)])
+                .unwrap();
+            assert_eq!(result, Value::Int(3));
+        });
+    }
+
+    #[test]
+    fn heap_arguments_should_stay_alive_during_call() {
+        //collect on every allocation so that unrooted argument would be freed
+        with_gc_vm(unsafe { GC::new(1) }, |vm| {
+            run(vm, PROGRAM).unwrap();
+
+            let long = vm.gc.new_string("long enough to live on heap");
+            let list = vm.gc.store(vec![Value::Int(1), long]);
+            let result = vm
+                .call_function(&test_module(), "second", vec![list])
+                .unwrap();
+            assert_eq!(result.unwrap_any_str(), Some("long enough to live on heap"));
+        });
+    }
+
+    #[test]
+    fn bad_calls_should_be_reported() {
+        with_vm(|vm| {
+            run(vm, PROGRAM).unwrap();
+            let module = test_module();
+
+            assert_eq!(
+                call_error(vm, &module, "add", vec![Value::Int(1)]),
+                InterpretErrorKind::TypeError {
+                    message: "too few arguments: expected 2 args but got 1".to_string()
+                }
+            );
+            assert_eq!(
+                call_error(vm, &module, "missing", vec![]),
+                InterpretErrorKind::NameError {
+                    name: "missing".to_string()
+                }
+            );
+            assert_eq!(
+                call_error(vm, &Module::from_dot_notation("not_loaded"), "add", vec![]),
+                InterpretErrorKind::NameError {
+                    name: "add".to_string()
+                }
+            );
+        });
+    }
+}
+
+mod stepping {
+    use super::{compile, with_gc_vm, with_vm};
+    use crate::data::gc::GC;
+    use crate::data::objects::Value;
+    use crate::execution::debugger::{Position, Step, Stepper};
+
+    const PROGRAM: &str = "def twice(x) =
+    var doubled = x * 2
+    doubled
+var a = list(1, 2, 3)
+var b = twice(a[2])
+b + 1";
+
+    #[test]
+    fn resume_should_stop_at_breakpoint() {
+        //collect on every allocation to check that stepping keeps roots alive
+        with_gc_vm(unsafe { GC::new(1) }, |vm| {
+            let pointer = compile(vm, PROGRAM);
+            let mut stepper = Stepper::new(vm, pointer);
+            stepper.add_breakpoint(2);
+
+            assert_eq!(
+                stepper.resume().unwrap(),
+                Step::Paused(Position {
+                    line: 2,
+                    call_depth: 2
+                })
+            );
+            assert_eq!(stepper.resume().unwrap(), Step::Finished(Value::Int(7)));
+            //finished program stays finished
+            assert_eq!(stepper.step().unwrap(), Step::Finished(Value::Int(7)));
+        });
+    }
+
+    #[test]
+    fn step_should_execute_single_instruction() {
+        with_vm(|vm| {
+            let pointer = compile(vm, "var a = 1\nvar b = 2");
+            let mut stepper = Stepper::new(vm, pointer);
+            let mut lines = vec![];
+            while let Step::Paused(position) = stepper.step().unwrap() {
+                lines.push(position.line);
+            }
+            assert!(lines.contains(&1));
+            assert!(lines.contains(&2));
+            assert_eq!(stepper.vm().eval_expr("1").unwrap(), Value::Int(1));
+        });
+    }
+}
+
+mod heap_report {
+    use super::{compile, with_vm};
+
+    const PROGRAM: &str = "var numbers = list(1, 2, 3)
+var name = \"long enough to live on heap\"
+def make_closure(x) = () => x
+def temporaries =
+    var discarded = list(list(1), list(2))
+    var adder = make_closure(1)
+    adder()
+assert temporaries() == 1";
+
+    #[test]
+    fn only_reachable_objects_should_survive_collection() {
+        with_vm(|vm| {
+            let pointer = compile(vm, PROGRAM);
+            vm.run(pointer.clone()).unwrap();
+
+            let report = unsafe { vm.heap_report(&[pointer]) };
+            //only global list survives, temporary lists and closure are freed
+            assert_eq!(report.count("Vector"), 1, "{}", report);
+            assert_eq!(report.count("Closure"), 0, "{}", report);
+        });
+    }
+}
+
+mod reset {
+    use super::{run, with_vm};
+
+    const PROGRAM: &str = "var numbers = list(1, 2, 3)
+def make_closure(x) = () => x
+var closure = make_closure(1)";
+
+    #[test]
+    fn reset_should_free_everything_and_isolate_programs() {
+        with_vm(|vm| {
+            run(vm, PROGRAM).unwrap();
+
+            unsafe { vm.reset() };
+            assert_eq!(unsafe { vm.heap_report(&[]) }.total(), 0);
+
+            //globals of previous program are gone
+            assert!(run(vm, "numbers").is_err());
+            run(vm, "var numbers = list(4)\nassert numbers == list(4)").unwrap();
+        });
+    }
+}
+
+mod partial_application {
+    use crate::data::objects::{Partial, Value};
+    use crate::execution::arity::Arity;
+
+    #[test]
+    fn filled_middle_argument_should_leave_outer_holes_in_order() {
+        let args = vec![Value::Blank, Value::Int(1), Value::Blank];
+        let partial = Partial::new(Value::Builtin(0), Arity::exact(3), args);
+        assert_eq!(partial.get_arity(), Arity::Exact(2));
+
+        let filled = partial.substitute(vec![Value::Int(0), Value::Int(2)]);
+        assert_eq!(filled.get_arity(), Arity::Exact(0));
+        assert_eq!(
+            filled.args,
+            vec![Value::Int(0), Value::Int(1), Value::Int(2)]
+        );
+    }
+
+    #[test]
+    fn partially_filled_vararg_should_stay_open() {
+        let args = vec![Value::Int(1), Value::Blank];
+        let partial = Partial::new(Value::Builtin(0), Arity::at_least(1), args);
+        assert_eq!(partial.get_arity(), Arity::AtLeast(1));
+
+        let filled = partial.substitute(vec![Value::Int(2), Value::Int(3)]);
+        assert_eq!(filled.get_arity(), Arity::AtLeast(0));
+        assert_eq!(
+            filled.args,
+            vec![Value::Int(1), Value::Int(2), Value::Int(3)]
+        );
+    }
+}